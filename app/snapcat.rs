@@ -3,30 +3,115 @@
 //! This binary provides access to the snapcat library functionality,
 //! walking a directory tree and outputting the result in various formats.
 
-use clap::{Parser, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 #[cfg(feature = "streaming")]
 use snapcat::SnapcatStream;
-use snapcat::{BinaryDetection, SnapcatBuilder, SnapcatOptions, SnapcatResult, output, snapcat};
-#[cfg(feature = "streaming")]
-use std::io::{self, Write};
-use std::path::PathBuf;
+use snapcat::{
+    BinaryDetection, ScanStats, SnapcatBuilder, SnapcatOptions, SnapcatResult, TreeStyle, output,
+    snapcat,
+};
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::time::SystemTime;
+
+/// Name of the config file auto-discovered in the root directory.
+const CONFIG_FILE_NAME: &str = ".snapcat.toml";
 
 /// snapcat — fast directory snapshot tool
 #[derive(Parser)]
 #[command(name = "snapcat", version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Without a subcommand, the arguments of `scan`
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Snapshot a directory tree (the default when no subcommand is given)
+    Scan(ScanArgs),
+    /// List the files added, removed and changed between two snapshots
+    Diff(DiffArgs),
+    /// Print aggregate statistics about a directory tree
+    Stats(StatsArgs),
+}
+
+#[derive(Args)]
+struct ScanArgs {
     /// Root directory (default current dir)
     #[arg(default_value = ".")]
     root: PathBuf,
 
+    #[command(flatten)]
+    filters: FilterArgs,
+
     /// Output format
     #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
     format: OutputFormat,
 
-    /// Binary detection strategy
-    #[arg(long, default_value = "simple", value_parser = parse_binary_detection)]
-    binary_detection: BinaryDetection,
+    /// Write output to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Print aggregate statistics instead of the snapshot (same as the `stats` subcommand)
+    #[arg(long)]
+    stats: bool,
+
+    /// Pretty output (indented JSON or formatted markdown/text)
+    #[arg(short, long)]
+    pretty: bool,
+
+    /// Tree branch characters: unicode, ascii or spaces [default: unicode]
+    #[arg(long, value_parser = parse_tree_style)]
+    tree_style: Option<TreeStyle>,
+
+    /// Color the tree output (ignored when NO_COLOR is set or stdout is not a terminal)
+    #[arg(long)]
+    color: bool,
+
+    /// Operation mode
+    #[arg(long, value_enum, default_value_t = Mode::Normal)]
+    mode: Mode,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Earlier snapshot: a directory, or a JSON snapshot written by `snapcat`
+    a: PathBuf,
+
+    /// Later snapshot: a directory, or a JSON snapshot written by `snapcat`
+    b: PathBuf,
+
+    #[command(flatten)]
+    filters: FilterArgs,
+}
+
+#[derive(Args)]
+struct StatsArgs {
+    /// Root directory (default current dir)
+    #[arg(default_value = ".")]
+    root: PathBuf,
+
+    #[command(flatten)]
+    filters: FilterArgs,
+
+    /// Write output to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Flags selecting the files of a scan and how they are read.
+#[derive(Args, Clone)]
+struct FilterArgs {
+    /// Binary detection strategy [default: simple]
+    #[arg(long, value_parser = parse_binary_detection)]
+    binary_detection: Option<BinaryDetection>,
 
     /// Max depth (unlimited if not set)
     #[arg(long)]
@@ -36,17 +121,29 @@ struct Cli {
     #[arg(short = 'I', long = "ignore")]
     ignore_patterns: Vec<String>,
 
+    /// Read ignore patterns from a file, one per line (can be repeated)
+    #[arg(long, value_name = "PATH")]
+    ignore_file: Vec<PathBuf>,
+
     /// File size limit in bytes (files larger will have content omitted)
     #[arg(long)]
     file_size_limit: Option<u64>,
 
-    /// Pretty output (indented JSON or formatted markdown/text)
-    #[arg(short, long)]
-    pretty: bool,
+    /// Do not read file contents; list the tree, paths and sizes only
+    #[arg(long)]
+    no_content: bool,
 
-    /// Enable color (tree only)
+    /// Read newline-separated file paths to process from stdin instead of walking
     #[arg(long)]
-    color: bool,
+    from_stdin: bool,
+
+    /// Only include files modified after this RFC 3339 timestamp
+    #[arg(long, value_parser = parse_timestamp)]
+    modified_after: Option<SystemTime>,
+
+    /// Only include files modified before this RFC 3339 timestamp
+    #[arg(long, value_parser = parse_timestamp)]
+    modified_before: Option<SystemTime>,
 
     /// Include hidden files
     #[arg(long)]
@@ -59,10 +156,6 @@ struct Cli {
     /// Disable .gitignore handling
     #[arg(long)]
     no_gitignore: bool,
-
-    /// Operation mode
-    #[arg(long, value_enum, default_value_t = Mode::Normal)]
-    mode: Mode,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
@@ -74,13 +167,25 @@ enum Mode {
     Streaming,
 }
 
+/// How and where the result is written.
+struct Output {
+    format: OutputFormat,
+    pretty: bool,
+    color: bool,
+    stats: bool,
+    path: Option<PathBuf>,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
 enum OutputFormat {
     Json,
+    JsonLines,
     Tree,
     Paths,
     Markdown,
     Text,
+    Yaml,
+    Html,
 }
 
 /// Parse string into BinaryDetection enum.
@@ -89,56 +194,242 @@ fn parse_binary_detection(s: &str) -> Result<BinaryDetection, String> {
         "simple" => Ok(BinaryDetection::Simple),
         "accurate" => Ok(BinaryDetection::Accurate),
         "none" => Ok(BinaryDetection::None),
+        "extension" => Ok(BinaryDetection::Extension),
         _ => Err(format!("invalid binary detection method: {}", s)),
     }
 }
 
-impl Cli {
-    fn into_options(self) -> (SnapcatOptions, OutputFormat, Mode, bool, bool) {
-        let mut builder = SnapcatBuilder::new(self.root)
-            .respect_gitignore(!self.no_gitignore)
-            .include_hidden(self.hidden)
-            .follow_links(self.follow_links)
-            .ignore_patterns(self.ignore_patterns)
-            .file_size_limit(self.file_size_limit)
-            .binary_detection(self.binary_detection);
+/// Parse string into TreeStyle enum.
+fn parse_tree_style(s: &str) -> Result<TreeStyle, String> {
+    match s {
+        "unicode" => Ok(TreeStyle::Unicode),
+        "ascii" => Ok(TreeStyle::Ascii),
+        "spaces" => Ok(TreeStyle::Spaces),
+        _ => Err(format!("invalid tree style: {}", s)),
+    }
+}
 
-        builder = if let Some(depth) = self.max_depth {
-            builder.max_depth(depth)
-        } else {
-            builder.no_limit_depth()
+/// Parse an RFC 3339 timestamp such as `2024-01-31T12:00:00Z`.
+fn parse_timestamp(s: &str) -> Result<SystemTime, String> {
+    humantime::parse_rfc3339_weak(s).map_err(|e| format!("invalid timestamp '{}': {}", s, e))
+}
+
+impl FilterArgs {
+    /// Applies the flags on top of `base` for the scan of `root`; flags that were given win.
+    fn into_builder(self, base: SnapcatOptions, root: &Path) -> SnapcatBuilder {
+        let mut builder = SnapcatBuilder::from(base).root(root);
+        if self.no_gitignore {
+            builder = builder.respect_gitignore(false);
+        }
+        if self.hidden {
+            builder = builder.include_hidden(true);
+        }
+        if self.follow_links {
+            builder = builder.follow_links(true);
+        }
+        // Patterns from files come first, so `-I` flags win under last-match-wins.
+        let mut ignore_patterns: Vec<String> = self
+            .ignore_file
+            .iter()
+            .flat_map(|path| read_ignore_file(path))
+            .collect();
+        ignore_patterns.extend(self.ignore_patterns);
+        if !ignore_patterns.is_empty() {
+            builder = builder.ignore_patterns(ignore_patterns);
+        }
+        if self.no_content {
+            builder = builder.read_content(false).include_file_size(true);
+        }
+        if let Some(limit) = self.file_size_limit {
+            builder = builder.file_size_limit(Some(limit));
+        }
+        if let Some(method) = self.binary_detection {
+            builder = builder.binary_detection(method);
+        }
+        if let Some(depth) = self.max_depth {
+            builder = builder.max_depth(depth);
+        }
+        if self.modified_after.is_some() {
+            builder = builder.modified_after(self.modified_after);
+        }
+        if self.modified_before.is_some() {
+            builder = builder.modified_before(self.modified_before);
+        }
+        if self.from_stdin {
+            builder = builder.explicit_paths(Some(read_stdin_paths()));
+        }
+        builder
+    }
+}
+
+impl ScanArgs {
+    /// Applies the command-line flags on top of `base`; flags that were given win.
+    fn into_options(self, base: SnapcatOptions) -> (SnapcatOptions, Output, Mode) {
+        let mut builder = self.filters.into_builder(base, &self.root);
+        if let Some(style) = self.tree_style {
+            builder = builder.tree_style(style);
+        }
+
+        let output = Output {
+            format: self.format,
+            pretty: self.pretty,
+            color: self.color,
+            stats: self.stats,
+            path: self.output,
         };
+        (builder.build(), output, self.mode)
+    }
+}
+
+/// Reads newline-separated glob patterns from a file, skipping blank lines and `#` comments.
+fn read_ignore_file(path: &Path) -> Vec<String> {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read ignore file {}: {}", path.display(), e);
+        exit(1);
+    });
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
 
-        (
-            builder.build(),
-            self.format,
-            self.mode,
-            self.pretty,
-            self.color,
-        )
+/// Reads newline-separated paths from stdin, skipping blank lines.
+fn read_stdin_paths() -> Vec<PathBuf> {
+    io::stdin()
+        .lines()
+        .map(|line| {
+            line.unwrap_or_else(|e| {
+                eprintln!("Failed to read stdin: {}", e);
+                exit(1);
+            })
+        })
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| PathBuf::from(line.trim()))
+        .collect()
+}
+
+/// Loads `.snapcat.toml` from the root directory, or the defaults if there is none.
+fn load_config(root: &Path) -> SnapcatOptions {
+    let path = root.join(CONFIG_FILE_NAME);
+    if !path.is_file() {
+        return SnapcatOptions::default();
     }
+    SnapcatOptions::from_toml_file(&path).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        exit(1);
+    })
 }
 
 fn main() {
     let cli = Cli::parse();
-    let (options, format, _mode, pretty, color) = cli.into_options();
+    match cli.command.unwrap_or(Command::Scan(cli.scan)) {
+        Command::Scan(args) => run_scan(args),
+        Command::Diff(args) => run_diff(args),
+        Command::Stats(args) => {
+            let base = load_config(&args.root);
+            let options = args.filters.into_builder(base, &args.root).build();
+            let output = Output {
+                format: OutputFormat::Text,
+                pretty: false,
+                color: false,
+                stats: true,
+                path: args.output,
+            };
+            run_normal(options, &output);
+        }
+    }
+}
 
-    #[cfg(feature = "streaming")]
-    if mode == Mode::Streaming {
-        run_streaming(&options, pretty);
-        return;
+fn run_scan(args: ScanArgs) {
+    let base = load_config(&args.root);
+    let (mut options, mut output, mode) = args.into_options(base);
+
+    match mode {
+        Mode::Normal => {}
+        Mode::TreeOnly => output.format = OutputFormat::Tree,
+        Mode::PathsOnly => output.format = OutputFormat::Paths,
+        #[cfg(feature = "streaming")]
+        Mode::Streaming => {
+            run_streaming(&options, output.pretty);
+            return;
+        }
     }
+    options.color_tree = output.format == OutputFormat::Tree && use_color(&output);
 
-    run_normal(options, format, pretty, color);
+    run_normal(options, &output);
 }
 
-fn run_normal(options: SnapcatOptions, format: OutputFormat, pretty: bool, color: bool) {
-    match snapcat(options) {
-        Ok(result) => output_result(&result, format, pretty, color),
-        Err(e) => {
-            eprintln!("Error: {}", e);
+/// Prints the files that differ between two snapshots, one per line, prefixed with `A`
+/// (added), `D` (removed) or `M` (changed) like `git diff --name-status`.
+fn run_diff(args: DiffArgs) {
+    let DiffArgs { a, b, filters } = args;
+    let before = load_snapshot(&a, &filters);
+    let after = load_snapshot(&b, &filters);
+    let diff = before.diff(&after);
+
+    let mut lines: Vec<(&Path, char)> = Vec::new();
+    lines.extend(diff.added.iter().map(|path| (path.as_path(), 'A')));
+    lines.extend(diff.removed.iter().map(|path| (path.as_path(), 'D')));
+    lines.extend(diff.changed.iter().map(|path| (path.as_path(), 'M')));
+    lines.sort();
+    let mut stdout = io::stdout().lock();
+    for (path, status) in lines {
+        if writeln!(stdout, "{}\t{}", status, path.display()).is_err() {
+            eprintln!("Failed to write to stdout");
+            exit(1);
+        }
+    }
+}
+
+/// Reads a JSON snapshot from a file, or scans a directory with the given flags.
+fn load_snapshot(path: &Path, filters: &FilterArgs) -> SnapcatResult {
+    if path.is_file() {
+        let text = std::fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Failed to read {}: {}", path.display(), e);
             exit(1);
+        });
+        return serde_json::from_str(&text).unwrap_or_else(|e| {
+            eprintln!("Invalid snapshot {}: {}", path.display(), e);
+            exit(1);
+        });
+    }
+    let options = filters
+        .clone()
+        .into_builder(load_config(path), path)
+        .build();
+    snapcat(options).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        exit(1);
+    })
+}
+
+/// Returns whether `--color` applies: only for a terminal on stdout and without `NO_COLOR`.
+fn use_color(output: &Output) -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    output.color && !no_color && output.path.is_none() && io::stdout().is_terminal()
+}
+
+fn run_normal(options: SnapcatOptions, output: &Output) {
+    let result = snapcat(options).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        exit(1);
+    });
+
+    let written = match &output.path {
+        Some(path) => File::create(path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write_output(&result, output, &mut writer)?;
+            writer.flush()
+        }),
+        None => write_output(&result, output, &mut io::stdout().lock()),
+    };
+    if let Err(e) = written {
+        match &output.path {
+            Some(path) => eprintln!("Failed to write {}: {}", path.display(), e),
+            None => eprintln!("Failed to write to stdout: {}", e),
         }
+        exit(1);
     }
 }
 
@@ -181,35 +472,48 @@ fn run_streaming(options: &SnapcatOptions, pretty: bool) {
     }
 }
 
-fn output_result(result: &SnapcatResult, format: OutputFormat, pretty: bool, _color: bool) {
-    match format {
-        OutputFormat::Json => {
-            let json = if pretty {
-                serde_json::to_string_pretty(result)
-            } else {
-                serde_json::to_string(result)
-            }
-            .unwrap_or_else(|e| {
-                eprintln!("JSON serialization error: {}", e);
-                exit(1);
-            });
-            println!("{}", json);
-        }
-        OutputFormat::Tree => {
-            println!("{}", result.tree);
-        }
+/// Writes the result in the chosen format; stdout and `--output` files get identical bytes.
+fn write_output<W: Write>(result: &SnapcatResult, output: &Output, w: &mut W) -> io::Result<()> {
+    if output.stats {
+        return write_stats(&result.stats(), w);
+    }
+    let pretty = output.pretty;
+    let format = match output.format {
+        OutputFormat::Tree => return writeln!(w, "{}", result.tree),
         OutputFormat::Paths => {
             for file in &result.files {
-                println!("{}", file.path.display());
+                writeln!(w, "{}", file.path.display())?;
             }
+            return Ok(());
         }
-        OutputFormat::Markdown => {
-            let out = output::format_result(result, output::OutputFormat::Markdown, pretty);
-            print!("{}", out);
-        }
-        OutputFormat::Text => {
-            let out = output::format_result(result, output::OutputFormat::Text, pretty);
-            print!("{}", out);
+        OutputFormat::Json => {
+            output::write_result(result, output::OutputFormat::Json, pretty, w)?;
+            return writeln!(w);
         }
+        OutputFormat::JsonLines => output::OutputFormat::JsonLines,
+        OutputFormat::Markdown => output::OutputFormat::Markdown,
+        OutputFormat::Text => output::OutputFormat::Text,
+        OutputFormat::Yaml => output::OutputFormat::Yaml,
+        OutputFormat::Html => output::OutputFormat::Html,
+    };
+    output::write_result(result, format, pretty, w)
+}
+
+/// Writes a small human-readable table of aggregate statistics.
+fn write_stats<W: Write>(stats: &ScanStats, w: &mut W) -> io::Result<()> {
+    writeln!(w, "Files:        {}", stats.file_count)?;
+    writeln!(w, "Total bytes:  {}", stats.total_bytes)?;
+    writeln!(w, "Binary files: {}", stats.binary_count)?;
+    writeln!(w, "Omitted:      {}", stats.omitted_count)?;
+    writeln!(w)?;
+    writeln!(w, "{:<12} {:>8}", "Extension", "Files")?;
+    for (extension, count) in &stats.by_extension {
+        let label = if extension.is_empty() {
+            "(none)"
+        } else {
+            extension
+        };
+        writeln!(w, "{:<12} {:>8}", label, count)?;
     }
+    Ok(())
 }
@@ -0,0 +1,191 @@
+//! Internal module for reading the members of an archive as a virtual directory tree.
+
+use crate::engine::ArchiveMember;
+use crate::error::SnapcatError;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Archive formats recognized from the file name.
+enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Detects the format from the archive's extension (case-insensitive).
+    fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Reads every file and directory member of a `.zip`, `.tar`, `.tar.gz` or `.tgz` archive.
+///
+/// Members whose path would escape the archive (absolute paths or `..` components) are
+/// dropped, as are special members such as symlinks. `size_limit` gives the limit of each
+/// member by its path: members over it are not decompressed past their first
+/// `sample_size` bytes.
+///
+/// # Errors
+///
+/// Returns [`SnapcatError::Config`] for an unsupported extension, [`SnapcatError::Io`] if
+/// the archive cannot be opened or a member cannot be read, and [`SnapcatError::InvalidPath`]
+/// if the archive is malformed.
+pub(crate) fn read_archive(
+    path: &Path,
+    size_limit: &dyn Fn(&Path) -> Option<u64>,
+    sample_size: usize,
+) -> Result<Vec<ArchiveMember>, SnapcatError> {
+    let format = ArchiveFormat::from_path(path).ok_or_else(|| {
+        SnapcatError::Config(format!(
+            "Unsupported archive format: {} (expected .zip, .tar, .tar.gz or .tgz)",
+            path.display()
+        ))
+    })?;
+    let file = File::open(path).map_err(|e| SnapcatError::io(path, e))?;
+    let limits = MemberLimits {
+        size_limit,
+        sample_size,
+    };
+    match format {
+        ArchiveFormat::Zip => read_zip(path, file, &limits),
+        ArchiveFormat::Tar => read_tar(path, BufReader::new(file), &limits),
+        ArchiveFormat::TarGz => read_tar(
+            path,
+            flate2::read::GzDecoder::new(BufReader::new(file)),
+            &limits,
+        ),
+    }
+}
+
+/// How much of each member to read; see [`read_archive`].
+struct MemberLimits<'a> {
+    size_limit: &'a dyn Fn(&Path) -> Option<u64>,
+    sample_size: usize,
+}
+
+impl MemberLimits<'_> {
+    /// Reads the bytes of the file member at `member_path` whose header gives its `size`.
+    ///
+    /// A member over its size limit only has its sample read. Otherwise at most one byte
+    /// past the limit is read, so a header understating the size cannot force the whole
+    /// member into memory. Returns the bytes and the member's size.
+    fn read(&self, member_path: &Path, size: u64, reader: impl Read) -> io::Result<(Vec<u8>, u64)> {
+        let limit = (self.size_limit)(member_path);
+        let max = match limit {
+            Some(limit) if size > limit => self.sample_size as u64,
+            Some(limit) => limit.saturating_add(1),
+            None => u64::MAX,
+        };
+        let mut bytes = Vec::new();
+        reader.take(max).read_to_end(&mut bytes)?;
+        let size = size.max(bytes.len() as u64);
+        Ok((bytes, size))
+    }
+}
+
+/// Reads the members of a zip archive.
+fn read_zip(
+    path: &Path,
+    file: File,
+    limits: &MemberLimits<'_>,
+) -> Result<Vec<ArchiveMember>, SnapcatError> {
+    let invalid =
+        |e: zip::result::ZipError| SnapcatError::InvalidPath(format!("{}: {}", path.display(), e));
+    let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(invalid)?;
+    let mut members = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let mut member = archive.by_index(index).map_err(invalid)?;
+        let Some(member_path) = member.enclosed_name().and_then(|p| sanitize(&p)) else {
+            continue;
+        };
+        let is_dir = member.is_dir();
+        let (bytes, size) = if is_dir {
+            (Vec::new(), 0)
+        } else {
+            let size = member.size();
+            limits
+                .read(&member_path, size, &mut member)
+                .map_err(|e| SnapcatError::io(path.join(&member_path), e))?
+        };
+        members.push(ArchiveMember {
+            path: member_path,
+            is_dir,
+            modified: None,
+            size,
+            bytes,
+        });
+    }
+    Ok(members)
+}
+
+/// Reads the members of a tar stream.
+fn read_tar(
+    path: &Path,
+    reader: impl Read,
+    limits: &MemberLimits<'_>,
+) -> Result<Vec<ArchiveMember>, SnapcatError> {
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive.entries().map_err(|e| SnapcatError::io(path, e))?;
+    let mut members = Vec::new();
+    for entry in entries {
+        let mut entry = entry.map_err(|e| SnapcatError::io(path, e))?;
+        let entry_type = entry.header().entry_type();
+        if !entry_type.is_file() && !entry_type.is_dir() {
+            continue;
+        }
+        let member_path = entry.path().map_err(|e| SnapcatError::io(path, e))?;
+        let Some(member_path) = sanitize(&member_path) else {
+            continue;
+        };
+        let modified = entry
+            .header()
+            .mtime()
+            .ok()
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        let (bytes, size) = if entry_type.is_file() {
+            let size = entry
+                .header()
+                .size()
+                .map_err(|e| SnapcatError::io(path, e))?;
+            limits
+                .read(&member_path, size, &mut entry)
+                .map_err(|e| SnapcatError::io(path.join(&member_path), e))?
+        } else {
+            (Vec::new(), 0)
+        };
+        members.push(ArchiveMember {
+            path: member_path,
+            is_dir: entry_type.is_dir(),
+            modified,
+            size,
+            bytes,
+        });
+    }
+    Ok(members)
+}
+
+/// Keeps only the named components of a member path, rejecting paths that are empty or
+/// would escape the archive root.
+fn sanitize(path: &Path) -> Option<PathBuf> {
+    let mut clean = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => clean.push(name),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!clean.as_os_str().is_empty()).then_some(clean)
+}
@@ -1,224 +1,2495 @@
 //! Core engine for directory walking and file processing.
 
 use crate::error::SnapcatError;
-use crate::options::{BinaryDetection, SnapcatOptions};
-use crate::tree::build_tree_from_entries;
-use crate::types::{FileEntry, SnapcatResult};
+#[cfg(any(feature = "archive", feature = "git"))]
+use crate::options::Source;
+use crate::options::{
+    BinaryDetection, BinaryHandling, ErrorMode, FollowLinks, LineEnding, ProgressCallback,
+    SnapcatOptions, SortOrder, Utf8Policy, WalkErrorPolicy,
+};
+use crate::output::language_for_path;
+#[cfg(feature = "streaming")]
+use crate::tree::TreeBuilder;
+use crate::tree::{EntryKind, build_tree_from_entries, strip_leading_components};
+use crate::types::{FileEntry, ProgressEvent, SkipReason, SnapcatResult};
+use base64::Engine as _;
 use ignore::WalkBuilder;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
 use std::fs::{self, File};
-use std::io::{BufReader, Read};
+use std::io::{self, BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(all(feature = "streaming", feature = "parallel"))]
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
 #[cfg(feature = "logging")]
 use tracing;
 
+/// Name of the snapcat-specific ignore file honored by default.
+const SNAPCATIGNORE_FILE_NAME: &str = ".snapcatignore";
+
 /// Internal walker that integrates ignore rules and glob patterns.
 struct Walker {
-    inner: ignore::Walk,
-    #[allow(dead_code)]
-    matcher: Option<globset::GlobSet>,
+    builder: WalkBuilder,
+    /// Whether to walk with [`ignore::WalkParallel`]; see [`SnapcatOptions::parallel_walk`].
+    #[cfg(feature = "parallel")]
+    parallel: bool,
+    follow_links: bool,
+    /// Whether to drop entries already yielded through another, overlapping root.
+    dedup: bool,
+    walk_errors: WalkErrorPolicy,
+    /// Paths rejected by the entry filter, recorded when `collect_skip_reasons` is set.
+    skipped: Option<SkipLog>,
+    /// Paths excluded by `.gitignore` files, recorded when `report_gitignore_exclusions`
+    /// is set.
+    gitignored: Option<PathLog>,
+    /// Flag that stops the walk when set; see [`SnapcatOptions::cancel`].
+    cancel: Option<Arc<AtomicBool>>,
+    /// Records the yielded paths that are symbolic links.
+    cache: PathCache,
+}
+
+/// Skip reasons shared with the walker's entry filter.
+type SkipLog = Arc<Mutex<Vec<(PathBuf, SkipReason)>>>;
+
+/// Paths recorded by the walker's entry filter.
+type PathLog = Arc<Mutex<Vec<PathBuf>>>;
+
+/// What the walk already learned about the walked paths: which are symbolic links (from
+/// the walker's entries) and, when sorting by size, their metadata, so processing does not
+/// `lstat` or `stat` them again.
+#[derive(Clone, Default)]
+struct PathCache {
+    symlinks: Arc<Mutex<HashSet<PathBuf>>>,
+    stats: Arc<Mutex<HashMap<PathBuf, FileStat>>>,
+}
+
+impl PathCache {
+    fn record_symlink(&self, path: PathBuf) {
+        self.symlinks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path);
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        self.symlinks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(path)
+    }
+
+    /// Returns the target of `path` if it was recorded as a symbolic link.
+    fn symlink_target(&self, path: &Path) -> Option<PathBuf> {
+        self.is_symlink(path)
+            .then(|| fs::read_link(path).ok())
+            .flatten()
+    }
+
+    /// Stats `path` for sorting by size and keeps its metadata for processing.
+    fn size(&self, path: &Path) -> u64 {
+        let Ok(metadata) = fs::metadata(path) else {
+            return 0;
+        };
+        let stat = FileStat::from(metadata);
+        self.stats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path.to_path_buf(), stat);
+        stat.len
+    }
+
+    /// Returns the metadata kept for `path`, if processing needs it.
+    fn cached_stat(&self, path: &Path, options: &SnapcatOptions) -> Option<FileStat> {
+        if !needs_metadata(options) {
+            return None;
+        }
+        let stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        stats.get(path).copied()
+    }
+
+    /// Like [`stat_if_needed`], reusing the metadata kept for `path`.
+    fn stat(
+        &self,
+        path: &Path,
+        options: &SnapcatOptions,
+    ) -> Result<Option<FileStat>, SnapcatError> {
+        match self.cached_stat(path, options) {
+            Some(stat) => Ok(Some(stat)),
+            None => stat_if_needed(path, options),
+        }
+    }
+}
+
+/// `.gitignore` rules evaluated by snapcat instead of the walker, so that the paths they
+/// exclude can be reported.
+///
+/// As in the walker, a path is checked against the `.gitignore` files of its ancestors up
+/// to the root of its Git repository, the deepest matching rule wins, and nothing is
+/// ignored outside a repository.
+#[derive(Default)]
+struct GitignoreRules {
+    /// The `.gitignore` of each directory seen so far (`None` if it has none), and whether
+    /// the directory is the root of a repository.
+    by_dir: Mutex<HashMap<PathBuf, (Option<ignore::gitignore::Gitignore>, bool)>>,
+}
+
+impl GitignoreRules {
+    /// Returns whether the path is excluded by a `.gitignore` file.
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(path) = std::path::absolute(path) else {
+            return false;
+        };
+        let mut by_dir = self.by_dir.lock().unwrap_or_else(|e| e.into_inner());
+        let mut verdict = None;
+        for dir in path.ancestors().skip(1) {
+            let (gitignore, is_repo_root) = by_dir
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| Self::load(dir));
+            if verdict.is_none()
+                && let Some(gitignore) = gitignore
+            {
+                match gitignore.matched(&path, is_dir) {
+                    ignore::Match::Ignore(_) => verdict = Some(true),
+                    ignore::Match::Whitelist(_) => verdict = Some(false),
+                    ignore::Match::None => {}
+                }
+            }
+            if *is_repo_root {
+                return verdict.unwrap_or(false);
+            }
+        }
+        false
+    }
+
+    /// Parses the `.gitignore` of a directory and checks whether it holds a `.git`.
+    fn load(dir: &Path) -> (Option<ignore::gitignore::Gitignore>, bool) {
+        let file = dir.join(".gitignore");
+        let gitignore = file.is_file().then(|| {
+            let mut builder = ignore::gitignore::GitignoreBuilder::new(dir);
+            // Invalid lines are skipped, as the walker does.
+            builder.add(&file);
+            builder
+                .build()
+                .unwrap_or_else(|_| ignore::gitignore::Gitignore::empty())
+        });
+        (gitignore, dir.join(".git").exists())
+    }
+}
+
+/// Compiles a list of glob patterns into a [`globset::GlobSet`].
+///
+/// Returns `None` when the list is empty.
+fn build_glob_set(
+    patterns: &[String],
+    case_insensitive: bool,
+) -> Result<Option<globset::GlobSet>, SnapcatError> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut glob_builder = globset::GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = globset::GlobBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|e| {
+                SnapcatError::Walk(format!("Invalid glob pattern '{}': {}", pattern, e))
+            })?;
+        glob_builder.add(glob);
+    }
+    glob_builder
+        .build()
+        .map(Some)
+        .map_err(|e| SnapcatError::Walk(format!("Failed to build glob set: {}", e)))
+}
+
+/// Compiled ignore patterns, as used for `ignore_patterns`.
+///
+/// Build one once with [`IgnoreMatcher::new`] (or from a prebuilt [`globset::GlobSet`]) and
+/// pass it to [`SnapcatBuilder::ignore_matcher`] to reuse it across scans without
+/// recompiling the patterns.
+///
+/// [`SnapcatBuilder::ignore_matcher`]: crate::SnapcatBuilder::ignore_matcher
+#[derive(Clone)]
+pub struct IgnoreMatcher {
+    globs: globset::GlobSet,
+    /// Whether the pattern at each index of `globs` is a negation.
+    negated: Vec<bool>,
+    /// Whether the pattern at each index of `globs` has no `/` and so also matches the name.
+    name_only: Vec<bool>,
+}
+
+impl IgnoreMatcher {
+    /// Compiles ignore patterns with the semantics of
+    /// [`SnapcatBuilder::ignore_patterns`](crate::SnapcatBuilder::ignore_patterns).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SnapcatError::Walk`] if a pattern is not a valid glob.
+    pub fn new(patterns: &[String], case_insensitive: bool) -> Result<Self, SnapcatError> {
+        Ok(Self::compile(patterns, case_insensitive)?
+            .unwrap_or_else(|| Self::from_glob_set(globset::GlobSet::empty())))
+    }
+
+    /// Wraps a prebuilt glob set; a path is ignored if any glob matches it (relative to the
+    /// root, unless `glob_match_absolute` is set) or its file name.
+    pub fn from_glob_set(globs: globset::GlobSet) -> Self {
+        Self {
+            negated: vec![false; globs.len()],
+            name_only: vec![true; globs.len()],
+            globs,
+        }
+    }
+
+    /// Compiles the ignore patterns; returns `None` when the list is empty.
+    fn compile(patterns: &[String], case_insensitive: bool) -> Result<Option<Self>, SnapcatError> {
+        let (globs, negated): (Vec<String>, Vec<bool>) = patterns
+            .iter()
+            .map(|pattern| match pattern.strip_prefix('!') {
+                Some(rest) => (rest.to_string(), true),
+                None => (pattern.clone(), false),
+            })
+            .unzip();
+        let name_only = globs.iter().map(|glob| !glob.contains('/')).collect();
+        Ok(build_glob_set(&globs, case_insensitive)?.map(|globs| Self {
+            globs,
+            negated,
+            name_only,
+        }))
+    }
+
+    /// Returns whether the path is ignored: the last matching pattern wins.
+    ///
+    /// Patterns are matched against the given path; like in `.gitignore`, patterns without a
+    /// `/` also match the file name alone.
+    fn is_ignored(&self, path: &Path) -> bool {
+        let by_name = path.file_name().map_or_else(Vec::new, |name| {
+            let mut matches = self.globs.matches(name);
+            matches.retain(|&i| self.name_only[i]);
+            matches
+        });
+        self.globs
+            .matches(path)
+            .into_iter()
+            .chain(by_name)
+            .max()
+            .is_some_and(|last| !self.negated[last])
+    }
+}
+
+impl fmt::Debug for IgnoreMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IgnoreMatcher")
+            .field("patterns", &self.globs.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<globset::GlobSet> for IgnoreMatcher {
+    fn from(globs: globset::GlobSet) -> Self {
+        Self::from_glob_set(globs)
+    }
 }
 
 impl Walker {
     /// Creates a new Walker based on the given options.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SnapcatError::InvalidPath`] if a root does not exist or is not a directory.
     fn new(options: &SnapcatOptions) -> Result<Self, SnapcatError> {
+        for root in options.roots() {
+            check_root(root)?;
+        }
+        // Hidden entries are filtered below when their skip reason has to be recorded.
+        let filter_hidden = !options.include_hidden && options.collect_skip_reasons;
         let mut builder = WalkBuilder::new(&options.root);
+        // To report what `.gitignore` files exclude, their rules are applied by the entry
+        // filter instead of the walker.
+        let gitignore = (options.respect_gitignore && options.report_gitignore_exclusions)
+            .then(GitignoreRules::default);
         builder
-            .git_ignore(options.respect_gitignore)
-            .hidden(!options.include_hidden)
-            .max_depth(options.max_depth)
-            .follow_links(options.follow_links)
+            .git_ignore(options.respect_gitignore && gitignore.is_none())
+            .git_global(options.respect_gitignore && options.git_global)
+            .git_exclude(options.respect_gitignore && options.git_exclude)
+            .hidden(!options.include_hidden && !filter_hidden)
+            .max_depth(walk_depth(options))
+            .follow_links(options.follow_links != FollowLinks::None)
             .ignore(false); // we handle ignore patterns ourselves
 
-        let matcher = if !options.ignore_patterns.is_empty() {
-            let mut glob_builder = globset::GlobSetBuilder::new();
-            for pattern in &options.ignore_patterns {
-                let glob = globset::Glob::new(pattern).map_err(|e| {
-                    SnapcatError::Walk(format!("Invalid glob pattern '{}': {}", pattern, e))
-                })?;
-                glob_builder.add(glob);
+        // Custom ignore files are not affected by `ignore(false)`.
+        if options.respect_snapcatignore {
+            builder.add_custom_ignore_filename(SNAPCATIGNORE_FILE_NAME);
+        }
+        for name in &options.custom_ignore_files {
+            builder.add_custom_ignore_filename(name);
+        }
+        for root in &options.extra_roots {
+            builder.add(root);
+        }
+        #[cfg(feature = "parallel")]
+        if options.parallel_walk {
+            builder.threads(options.thread_count.unwrap_or(0));
+        }
+
+        let filter = EntryFilter::new(options, filter_hidden)?;
+        let skipped = options.collect_skip_reasons.then(SkipLog::default);
+        let gitignored = gitignore.is_some().then(PathLog::default);
+
+        if filter.is_active() || skipped.is_some() || gitignore.is_some() {
+            let skipped = skipped.clone();
+            let gitignored = gitignored.clone();
+            builder.filter_entry(move |entry| {
+                let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                if let (Some(rules), Some(gitignored)) = (&gitignore, &gitignored)
+                    && entry.depth() > 0
+                    && rules.is_ignored(entry.path(), is_dir)
+                {
+                    let mut gitignored = gitignored.lock().unwrap_or_else(|e| e.into_inner());
+                    gitignored.push(entry.path().to_path_buf());
+                    return false;
+                }
+                let reason = filter.check(entry.path(), is_dir, entry.depth());
+                match (reason, &skipped) {
+                    (None, _) => true,
+                    (Some(reason), Some(skipped)) => {
+                        let mut skipped = skipped.lock().unwrap_or_else(|e| e.into_inner());
+                        skipped.push((entry.path().to_path_buf(), reason));
+                        false
+                    }
+                    (Some(_), None) => false,
+                }
+            });
+        }
+
+        Ok(Self {
+            builder,
+            #[cfg(feature = "parallel")]
+            parallel: options.parallel_walk,
+            follow_links: options.follow_links != FollowLinks::None,
+            dedup: !options.extra_roots.is_empty(),
+            walk_errors: options.walk_errors,
+            skipped,
+            gitignored,
+            cancel: options.cancel.clone(),
+            cache: PathCache::default(),
+        })
+    }
+
+    /// Converts the walker into an iterator over paths.
+    ///
+    /// When following symlinks, directories whose canonical path was already visited are
+    /// skipped together with their contents, and symlink loops reported by the walker are
+    /// dropped instead of failing the walk, so every real file is yielded once. Likewise,
+    /// entries reached again through an overlapping root are dropped. Other walker errors
+    /// are dropped as well with [`WalkErrorPolicy::Skip`]. Once the `cancel` flag is set,
+    /// a single [`SnapcatError::Cancelled`] is yielded and the walk stops.
+    ///
+    /// A parallel walk runs to completion here and its entries are then sorted by path, so
+    /// every directory still precedes its contents.
+    fn into_iter(self) -> impl Iterator<Item = Result<PathBuf, SnapcatError>> {
+        let entries = self.entries();
+        let cancel = self.cancel;
+        let follow_links = self.follow_links;
+        let dedup = self.dedup;
+        let cache = self.cache;
+        let skip_errors = self.walk_errors == WalkErrorPolicy::Skip;
+        let mut visited_dirs: HashSet<PathBuf> = HashSet::new();
+        let mut seen: HashSet<PathBuf> = HashSet::new();
+        let mut skipped_dir: Option<PathBuf> = None;
+
+        let paths = entries.filter_map(move |result| match result {
+            Ok(entry) => {
+                let path = entry.path();
+                if let Some(skipped) = &skipped_dir {
+                    if path.starts_with(skipped) {
+                        return None;
+                    }
+                    skipped_dir = None;
+                }
+                if follow_links && entry.file_type().is_some_and(|t| t.is_dir()) {
+                    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+                    if !visited_dirs.insert(canonical) {
+                        #[cfg(feature = "logging")]
+                        tracing::warn!("Skipping already visited directory: {}", path.display());
+                        skipped_dir = Some(path.to_path_buf());
+                        return None;
+                    }
+                }
+                if dedup {
+                    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+                    if !seen.insert(canonical) {
+                        return None;
+                    }
+                }
+                if entry.path_is_symlink() {
+                    cache.record_symlink(path.to_path_buf());
+                }
+                Some(Ok(path.to_path_buf()))
+            }
+            Err(e) if skip_errors || (follow_links && is_loop_error(&e)) => {
+                #[cfg(feature = "logging")]
+                tracing::warn!("{}", SnapcatError::Walk(e.to_string()));
+                None
+            }
+            Err(e) => Some(Err(SnapcatError::Walk(e.to_string()))),
+        });
+        until_cancelled(paths, cancel)
+    }
+
+    /// Returns the entries yielded by the walker, in walk order.
+    fn entries(&self) -> WalkEntries {
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            let entries = walk_parallel(self.builder.build_parallel(), self.cancel.as_deref());
+            // The filter logs were filled by several threads.
+            if let Some(gitignored) = &self.gitignored {
+                gitignored.lock().unwrap_or_else(|e| e.into_inner()).sort();
+            }
+            if let Some(skipped) = &self.skipped {
+                let mut skipped = skipped.lock().unwrap_or_else(|e| e.into_inner());
+                skipped.sort_by(|(a, _), (b, _)| a.cmp(b));
+            }
+            return Box::new(entries.into_iter());
+        }
+        Box::new(self.builder.build())
+    }
+
+    /// Collects all paths into a Vec.
+    fn collect_entries(self) -> Result<Vec<PathBuf>, SnapcatError> {
+        self.into_iter().collect()
+    }
+}
+
+/// Entries yielded by a sequential or parallel walk.
+type WalkEntries = Box<dyn Iterator<Item = Result<ignore::DirEntry, ignore::Error>> + Send>;
+
+/// Runs a parallel walk to completion and sorts its entries by path; errors come last.
+///
+/// Stops early once the `cancel` flag is set.
+#[cfg(feature = "parallel")]
+fn walk_parallel(
+    walk: ignore::WalkParallel,
+    cancel: Option<&AtomicBool>,
+) -> Vec<Result<ignore::DirEntry, ignore::Error>> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    walk.run(|| {
+        let tx = tx.clone();
+        Box::new(move |result| {
+            if is_cancelled(cancel) || tx.send(result).is_err() {
+                return ignore::WalkState::Quit;
             }
-            Some(
-                glob_builder
-                    .build()
-                    .map_err(|e| SnapcatError::Walk(format!("Failed to build glob set: {}", e)))?,
-            )
+            ignore::WalkState::Continue
+        })
+    });
+    drop(tx);
+    let mut entries: Vec<_> = rx.into_iter().collect();
+    entries.sort_by(|a, b| match (a, b) {
+        (Ok(a), Ok(b)) => a.path().cmp(b.path()),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Err(_)) => std::cmp::Ordering::Equal,
+    });
+    entries
+}
+
+/// Returns whether the `cancel` flag of a scan is set.
+fn is_cancelled(cancel: Option<&AtomicBool>) -> bool {
+    cancel.is_some_and(|flag| flag.load(Ordering::Relaxed))
+}
+
+/// Stops `items` once the `cancel` flag is set, yielding a single
+/// [`SnapcatError::Cancelled`] in place of the remaining items.
+fn until_cancelled<T>(
+    mut items: impl Iterator<Item = Result<T, SnapcatError>>,
+    cancel: Option<Arc<AtomicBool>>,
+) -> impl Iterator<Item = Result<T, SnapcatError>> {
+    let mut cancelled = false;
+    std::iter::from_fn(move || {
+        if cancelled {
+            return None;
+        }
+        if is_cancelled(cancel.as_deref()) {
+            cancelled = true;
+            return Some(Err(SnapcatError::Cancelled));
+        }
+        items.next()
+    })
+}
+
+/// The per-entry filters applied while walking: path length, hidden entries, ignore and
+/// include patterns, and file extensions.
+#[derive(Clone)]
+struct EntryFilter {
+    matcher: Option<IgnoreMatcher>,
+    include: Option<globset::GlobSet>,
+    extensions: Option<ExtensionFilter>,
+    max_path_length: Option<usize>,
+    /// Whether hidden entries are rejected here rather than by the walker itself.
+    filter_hidden: bool,
+    /// Roots stripped from paths before matching globs; empty with `glob_match_absolute`.
+    glob_roots: Vec<PathBuf>,
+}
+
+impl EntryFilter {
+    /// Compiles the filters from the options.
+    fn new(options: &SnapcatOptions, filter_hidden: bool) -> Result<Self, SnapcatError> {
+        Ok(Self {
+            matcher: match &options.ignore_matcher {
+                Some(matcher) => Some(matcher.clone()),
+                None => IgnoreMatcher::compile(
+                    &options.ignore_patterns,
+                    options.case_insensitive_globs,
+                )?,
+            },
+            include: build_glob_set(&options.include_patterns, options.case_insensitive_globs)?,
+            extensions: ExtensionFilter::new(options),
+            max_path_length: options.max_path_length,
+            filter_hidden,
+            glob_roots: if options.glob_match_absolute {
+                Vec::new()
+            } else {
+                options.roots().map(Path::to_path_buf).collect()
+            },
+        })
+    }
+
+    /// Returns whether any filter is configured.
+    fn is_active(&self) -> bool {
+        self.matcher.is_some()
+            || self.include.is_some()
+            || self.extensions.is_some()
+            || self.max_path_length.is_some()
+            || self.filter_hidden
+    }
+
+    /// Returns why an entry at the given walk depth is rejected, or `None` to keep it.
+    fn check(&self, path: &Path, is_dir: bool, depth: usize) -> Option<SkipReason> {
+        if self
+            .max_path_length
+            .is_some_and(|max| path.as_os_str().len() > max)
+        {
+            #[cfg(feature = "logging")]
+            tracing::debug!("Path too long, skipping: {}", path.display());
+            Some(SkipReason::PathTooLong)
+        } else if self.filter_hidden && depth > 0 && is_hidden(path) {
+            Some(SkipReason::Hidden)
+        } else if self
+            .matcher
+            .as_ref()
+            .is_some_and(|m| m.is_ignored(self.glob_path(path)))
+        {
+            Some(SkipReason::IgnoredGlob)
         } else {
-            None
+            // Directories are always traversed so nested matches stay reachable.
+            let included = is_dir
+                || (self
+                    .include
+                    .as_ref()
+                    .is_none_or(|i| i.is_match(self.glob_path(path)))
+                    && self.extensions.as_ref().is_none_or(|e| e.allows(path)));
+            (!included).then_some(SkipReason::IgnoredGlob)
+        }
+    }
+
+    /// Returns the path that globs are matched against: relative to the first root that
+    /// contains it, unless `glob_match_absolute` is set.
+    fn glob_path<'a>(&self, path: &'a Path) -> &'a Path {
+        self.glob_roots
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path)
+    }
+}
+
+/// Normalized `include_extensions` and `exclude_extensions`, stored as lowercase
+/// suffixes with a leading dot.
+#[derive(Clone)]
+struct ExtensionFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl ExtensionFilter {
+    /// Builds the filter, returning `None` when no extensions are configured.
+    fn new(options: &SnapcatOptions) -> Option<Self> {
+        if options.include_extensions.is_empty() && options.exclude_extensions.is_empty() {
+            return None;
+        }
+        let normalize = |extensions: &[String]| {
+            extensions
+                .iter()
+                .map(|ext| format!(".{}", ext.trim_start_matches('.').to_lowercase()))
+                .collect()
+        };
+        Some(Self {
+            include: normalize(&options.include_extensions),
+            exclude: normalize(&options.exclude_extensions),
+        })
+    }
+
+    /// Returns whether a file with this path passes the filter.
+    fn allows(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name() else {
+            return self.include.is_empty();
+        };
+        let name = name.to_string_lossy().to_lowercase();
+        let has = |ext: &String| name.len() > ext.len() && name.ends_with(ext.as_str());
+        (self.include.is_empty() || self.include.iter().any(has)) && !self.exclude.iter().any(has)
+    }
+}
+
+/// Returns the maximum walk depth, where the root's direct children are at depth 1.
+fn walk_depth(options: &SnapcatOptions) -> Option<usize> {
+    if options.depth_includes_root {
+        options.max_depth.map(|depth| depth.saturating_sub(1))
+    } else {
+        options.max_depth
+    }
+}
+
+/// Checks up front that a root is an existing directory, so a mistyped path gets a clear
+/// error instead of a generic walk error.
+fn check_root(root: &Path) -> Result<(), SnapcatError> {
+    match fs::metadata(root) {
+        Ok(metadata) if metadata.is_dir() => Ok(()),
+        Ok(_) => Err(SnapcatError::InvalidPath(format!(
+            "root is not a directory: {}",
+            root.display()
+        ))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(SnapcatError::InvalidPath(format!(
+            "root does not exist: {}",
+            root.display()
+        ))),
+        Err(e) => Err(SnapcatError::io(root, e)),
+    }
+}
+
+/// Returns whether the file name starts with a dot.
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+/// Returns whether a walker error reports a symlink loop.
+fn is_loop_error(error: &ignore::Error) -> bool {
+    match error {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => is_loop_error(err),
+        ignore::Error::Partial(errors) => errors.iter().all(is_loop_error),
+        _ => false,
+    }
+}
+
+/// Content read from a single file, before it is assembled into a [`FileEntry`].
+struct FileContent {
+    /// The decoded text, or a placeholder message when the content was omitted.
+    content: String,
+    /// Whether the file was detected as binary.
+    is_binary: bool,
+    /// Whether `content` holds the real file text rather than a placeholder.
+    is_text: bool,
+    /// Hex SHA-256 of the raw file bytes, if hashing was requested.
+    hash: Option<String>,
+    /// Name of the charset the content was decoded from, if detection was requested.
+    encoding: Option<&'static str>,
+    /// Whether `content` is the base64 of the raw bytes of a binary file.
+    is_base64: bool,
+    /// Why the content was omitted, if for a reason tracked by [`SkipReason`].
+    skip_reason: Option<SkipReason>,
+}
+
+impl FileContent {
+    /// Creates a placeholder for content that was not read.
+    fn omitted(message: &str, is_binary: bool) -> Self {
+        Self {
+            content: message.to_string(),
+            is_binary,
+            is_text: false,
+            hash: None,
+            encoding: None,
+            is_base64: false,
+            skip_reason: is_binary.then_some(SkipReason::Binary),
+        }
+    }
+
+    /// Creates the content of a text file decoded as UTF-8, normalizing its line endings
+    /// and expanding tabs.
+    fn text(content: String, options: &SnapcatOptions) -> Self {
+        Self {
+            content: normalize_text(content, options),
+            is_binary: false,
+            is_text: true,
+            hash: None,
+            encoding: None,
+            is_base64: false,
+            skip_reason: None,
+        }
+    }
+
+    /// Creates the empty content of a file that is not opened because `read_content` is
+    /// off; binary files are recognized by their extension alone.
+    fn not_read(path: &Path) -> Self {
+        Self {
+            content: String::new(),
+            is_binary: has_binary_extension(path),
+            is_text: false,
+            hash: None,
+            encoding: None,
+            is_base64: false,
+            skip_reason: None,
+        }
+    }
+
+    /// Creates the empty content of a file that is not read because `read_content` is off,
+    /// classified from its leading bytes for `sniff_binary`.
+    fn sniffed(path: &Path, sample: &[u8], options: &SnapcatOptions) -> Self {
+        Self {
+            is_binary: !has_text_bom(sample, options)
+                && is_binary_file(path, sample, &options.binary_detection),
+            ..Self::not_read(path)
+        }
+    }
+
+    /// Creates a placeholder for a file exceeding its size limit, `too_large_placeholder`
+    /// if set.
+    fn too_large(options: &SnapcatOptions) -> Self {
+        let message = options
+            .too_large_placeholder
+            .as_deref()
+            .unwrap_or(TOO_LARGE_PLACEHOLDER);
+        Self {
+            skip_reason: Some(SkipReason::TooLarge),
+            ..Self::omitted(message, false)
+        }
+    }
+
+    /// Creates the content of a binary file according to `binary_handling`.
+    ///
+    /// `bytes` is only encoded for [`BinaryHandling::Base64`]; otherwise a placeholder is
+    /// used, `binary_placeholder` if set.
+    fn binary(bytes: &[u8], options: &SnapcatOptions) -> Self {
+        match options.binary_handling {
+            BinaryHandling::Base64 => Self {
+                content: base64::engine::general_purpose::STANDARD.encode(bytes),
+                is_binary: true,
+                is_text: false,
+                hash: None,
+                encoding: None,
+                is_base64: true,
+                skip_reason: None,
+            },
+            BinaryHandling::Omit | BinaryHandling::Skip => Self::omitted(
+                options
+                    .binary_placeholder
+                    .as_deref()
+                    .unwrap_or(BINARY_PLACEHOLDER),
+                true,
+            ),
+        }
+    }
+
+    /// Attaches the hash of the raw file bytes.
+    fn with_hash(mut self, hash: Option<String>) -> Self {
+        self.hash = hash;
+        self
+    }
+}
+
+/// Incremental SHA-256 over a file's raw bytes.
+///
+/// Inactive (and free) unless the `hash` feature is enabled and `include_hash` or
+/// `dedup_content` is set.
+struct ContentHasher {
+    #[cfg(feature = "hash")]
+    inner: Option<sha2::Sha256>,
+}
+
+impl ContentHasher {
+    /// Creates a hasher that is active only if the options request hashing.
+    #[cfg_attr(not(feature = "hash"), allow(unused_variables))]
+    fn new(options: &SnapcatOptions) -> Self {
+        Self {
+            #[cfg(feature = "hash")]
+            inner: (options.include_hash || options.dedup_content)
+                .then(<sha2::Sha256 as sha2::Digest>::new),
+        }
+    }
+
+    /// Returns whether the hash will be computed.
+    fn is_active(&self) -> bool {
+        #[cfg(feature = "hash")]
+        return self.inner.is_some();
+        #[cfg(not(feature = "hash"))]
+        false
+    }
+
+    /// Feeds a chunk of raw bytes into the hash.
+    #[cfg_attr(not(feature = "hash"), allow(unused_variables))]
+    fn update(&mut self, bytes: &[u8]) {
+        #[cfg(feature = "hash")]
+        if let Some(hasher) = self.inner.as_mut() {
+            sha2::Digest::update(hasher, bytes);
+        }
+    }
+
+    /// Feeds the remainder of a reader into the hash.
+    #[cfg_attr(not(feature = "hash"), allow(unused_variables, unused_mut))]
+    fn update_reader(&mut self, mut reader: impl Read) -> io::Result<()> {
+        #[cfg(feature = "hash")]
+        if let Some(hasher) = self.inner.as_mut() {
+            io::copy(&mut reader, hasher)?;
+        }
+        Ok(())
+    }
+
+    /// Finishes the hash, returning its lowercase hex digest if active.
+    fn finish(self) -> Option<String> {
+        #[cfg(feature = "hash")]
+        return self.inner.map(|hasher| {
+            sha2::Digest::finalize(hasher)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect()
+        });
+        #[cfg(not(feature = "hash"))]
+        None
+    }
+}
+
+/// Placeholder content for files exceeding the size limit.
+const TOO_LARGE_PLACEHOLDER: &str = "[File too large, content omitted]";
+/// Placeholder content for files detected as binary.
+const BINARY_PLACEHOLDER: &str = "[Binary file, content omitted]";
+/// Placeholder content for files past the total content budget.
+const BUDGET_PLACEHOLDER: &str = "[Output size limit reached]";
+/// Placeholder content for symlinked files whose target is not read.
+const SYMLINK_PLACEHOLDER: &str = "[Symlink, content omitted]";
+
+/// Reads a file's content with binary detection and size limit.
+///
+/// `metadata` must be provided when a size limit applies; it is fetched once by the caller
+/// and shared with the rest of the entry. `size_limit` is the limit resolved for this file
+/// (see [`ContentFilter::size_limit`]). When hashing is requested, the hash always
+/// covers the file's real bytes, even if the content itself is omitted.
+fn read_file_content(
+    path: &Path,
+    metadata: Option<FileStat>,
+    size_limit: Option<u64>,
+    options: &SnapcatOptions,
+) -> Result<FileContent, SnapcatError> {
+    let mut hasher = ContentHasher::new(options);
+
+    if let (Some(limit), Some(metadata)) = (size_limit, metadata)
+        && metadata.len > limit
+    {
+        #[cfg(feature = "logging")]
+        tracing::debug!(
+            "File too large ({} > {}), skipping content",
+            metadata.len,
+            limit
+        );
+        if hasher.is_active() {
+            let file = File::open(path).map_err(|e| SnapcatError::io(path, e))?;
+            hasher
+                .update_reader(BufReader::new(file))
+                .map_err(|e| SnapcatError::io(path, e))?;
+        }
+        return Ok(FileContent::too_large(options).with_hash(hasher.finish()));
+    }
+
+    let file = File::open(path).map_err(|e| SnapcatError::io(path, e))?;
+    let mut reader = BufReader::new(file);
+
+    // Read the sample window for binary detection
+    let sample_size = options.binary_sample_size;
+    let mut bytes = Vec::with_capacity(sample_size);
+    let _ = reader
+        .by_ref()
+        .take(sample_size as u64)
+        .read_to_end(&mut bytes)
+        .map_err(|e| SnapcatError::io(path, e))?;
+
+    if !has_text_bom(&bytes, options) && is_binary_file(path, &bytes, &options.binary_detection) {
+        #[cfg(feature = "logging")]
+        tracing::debug!("Binary file detected: {}", path.display());
+        if options.binary_handling == BinaryHandling::Base64 {
+            reader
+                .read_to_end(&mut bytes)
+                .map_err(|e| SnapcatError::io(path, e))?;
+            hasher.update(&bytes);
+        } else {
+            hasher.update(&bytes);
+            hasher
+                .update_reader(reader)
+                .map_err(|e| SnapcatError::io(path, e))?;
+        }
+        return Ok(FileContent::binary(&bytes, options).with_hash(hasher.finish()));
+    }
+
+    let file_len = match metadata {
+        Some(metadata) => Some(metadata.len),
+        None if options.stream_threshold.is_some() => {
+            reader.get_ref().metadata().ok().map(|m| m.len())
+        }
+        None => None,
+    };
+    if let (Some(threshold), Some(len)) = (options.stream_threshold, file_len)
+        && len > threshold
+        && !detects_encoding(options)
+        && options.utf8_policy == Utf8Policy::Lossy
+    {
+        hasher.update(&bytes);
+        let content = read_text_chunked(reader, bytes, &mut hasher, len)
+            .map_err(|e| SnapcatError::io(path, e))?;
+        return Ok(FileContent::text(content, options).with_hash(hasher.finish()));
+    }
+
+    // Keep the sniffed bytes raw and decode the whole file at once, so a
+    // multi-byte character straddling the sniff boundary is not split.
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|e| SnapcatError::io(path, e))?;
+
+    hasher.update(&bytes);
+    Ok(text_content(path, bytes, options)?.with_hash(hasher.finish()))
+}
+
+/// Builds the content of a file that is not read because `read_content` is off; with
+/// `sniff_binary`, the file is opened for its first `binary_sample_size` bytes only.
+fn unread_file_content(path: &Path, options: &SnapcatOptions) -> Result<FileContent, SnapcatError> {
+    if !options.sniff_binary {
+        return Ok(FileContent::not_read(path));
+    }
+    let mut sample = Vec::with_capacity(options.binary_sample_size);
+    File::open(path)
+        .and_then(|file| {
+            file.take(options.binary_sample_size as u64)
+                .read_to_end(&mut sample)
+        })
+        .map_err(|e| SnapcatError::io(path, e))?;
+    Ok(FileContent::sniffed(path, &sample, options))
+}
+
+/// Builds the content of a file whose raw bytes are already in memory, applying the size
+/// limit and binary detection like [`read_file_content`].
+#[cfg(any(feature = "async", feature = "archive", feature = "git"))]
+fn content_from_bytes(
+    path: &Path,
+    bytes: Vec<u8>,
+    size_limit: Option<u64>,
+    options: &SnapcatOptions,
+) -> Result<FileContent, SnapcatError> {
+    let mut hasher = ContentHasher::new(options);
+    hasher.update(&bytes);
+    let too_large = size_limit.is_some_and(|limit| bytes.len() as u64 > limit);
+    let sample = &bytes[..bytes.len().min(options.binary_sample_size)];
+    let content = if too_large {
+        FileContent::too_large(options)
+    } else if !has_text_bom(sample, options)
+        && is_binary_file(path, sample, &options.binary_detection)
+    {
+        FileContent::binary(&bytes, options)
+    } else {
+        text_content(path, bytes, options)?
+    };
+    Ok(content.with_hash(hasher.finish()))
+}
+
+/// Size of the chunks read by [`read_text_chunked`].
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reads the rest of a text file in chunks, decoding UTF-8 as it goes so that the raw
+/// bytes are never held all at once. `pending` holds the bytes already read (and hashed);
+/// `len` is the expected size, used to reserve the content.
+///
+/// The result is the same as lossy decoding of the whole file.
+fn read_text_chunked(
+    mut reader: impl Read,
+    mut pending: Vec<u8>,
+    hasher: &mut ContentHasher,
+    len: u64,
+) -> io::Result<String> {
+    let mut content = String::with_capacity(usize::try_from(len).unwrap_or(0));
+    let mut chunk = vec![0; READ_CHUNK_SIZE];
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
         };
+        hasher.update(&chunk[..read]);
+        pending.extend_from_slice(&chunk[..read]);
+        let at_end = read == 0;
+        decode_utf8_chunk(&mut pending, &mut content, at_end);
+        if at_end {
+            return Ok(content);
+        }
+    }
+}
+
+/// Appends the decodable bytes of `pending` to `out`, replacing invalid sequences with
+/// U+FFFD, and removes them from `pending`.
+///
+/// Unless `at_end` is set, an incomplete character at the end of `pending` is kept there so
+/// that the next chunk can complete it.
+fn decode_utf8_chunk(pending: &mut Vec<u8>, out: &mut String, at_end: bool) {
+    let mut consumed = 0;
+    for chunk in pending.utf8_chunks() {
+        out.push_str(chunk.valid());
+        consumed += chunk.valid().len();
+        let invalid = chunk.invalid();
+        if invalid.is_empty() {
+            continue;
+        }
+        let truncated = std::str::from_utf8(invalid).is_err_and(|e| e.error_len().is_none());
+        if !at_end && truncated && consumed + invalid.len() == pending.len() {
+            break;
+        }
+        out.push(char::REPLACEMENT_CHARACTER);
+        consumed += invalid.len();
+    }
+    pending.drain(..consumed);
+}
+
+/// Returns whether charset detection is enabled.
+#[cfg_attr(not(feature = "encoding"), allow(unused_variables))]
+fn detects_encoding(options: &SnapcatOptions) -> bool {
+    #[cfg(feature = "encoding")]
+    return options.detect_encoding;
+    #[cfg(not(feature = "encoding"))]
+    false
+}
+
+/// File extensions treated as binary by [`BinaryDetection::Extension`].
+const BINARY_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "tif", "tiff", "psd", "pdf", "zip", "gz",
+    "tgz", "bz2", "xz", "zst", "7z", "rar", "tar", "jar", "war", "exe", "dll", "so", "dylib", "o",
+    "a", "lib", "obj", "class", "pyc", "wasm", "bin", "iso", "dmg", "mp3", "mp4", "m4a", "avi",
+    "mov", "mkv", "wav", "flac", "ogg", "webm", "ttf", "otf", "woff", "woff2", "eot", "sqlite",
+    "db",
+];
+
+/// Decides whether a file is binary, from its path and a sample of its leading bytes.
+///
+/// For built-in content-based methods, an empty sample (e.g. a sample size of 0) is always
+/// treated as text.
+fn is_binary_file(path: &Path, sample: &[u8], binary_detection: &BinaryDetection) -> bool {
+    match binary_detection {
+        BinaryDetection::Extension => has_binary_extension(path),
+        BinaryDetection::Custom(detector) => (detector.0)(path, sample),
+        _ if sample.is_empty() => false,
+        BinaryDetection::Simple => sample.contains(&0),
+        BinaryDetection::Accurate => content_inspector::inspect(sample).is_binary(),
+        BinaryDetection::None => false,
+    }
+}
+
+/// Returns whether the path has a well-known binary file extension (case-insensitive).
+fn has_binary_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            BINARY_EXTENSIONS
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(ext))
+        })
+}
+
+/// Returns whether the sample starts with a byte order mark and encoding detection is on.
+///
+/// Such files are text even if they contain NUL bytes (as UTF-16 does).
+#[cfg_attr(not(feature = "encoding"), allow(unused_variables))]
+fn has_text_bom(sample: &[u8], options: &SnapcatOptions) -> bool {
+    #[cfg(feature = "encoding")]
+    return options.detect_encoding && encoding_rs::Encoding::for_bom(sample).is_some();
+    #[cfg(not(feature = "encoding"))]
+    false
+}
+
+/// Builds the text content of a file from its raw bytes, handling invalid UTF-8 (or
+/// invalid bytes for the detected encoding) according to `utf8_policy`.
+fn text_content(
+    path: &Path,
+    bytes: Vec<u8>,
+    options: &SnapcatOptions,
+) -> Result<FileContent, SnapcatError> {
+    #[cfg(feature = "encoding")]
+    if options.detect_encoding {
+        let sample = &bytes[..bytes.len().min(options.binary_sample_size)];
+        let encoding = detect_encoding(sample, &bytes);
+        let (content, _, had_errors) = encoding.decode(&bytes);
+        if had_errors {
+            check_utf8_policy(path, options)?;
+            if options.utf8_policy == Utf8Policy::TreatInvalidAsBinary {
+                return Ok(FileContent::binary(&bytes, options));
+            }
+        }
+        return Ok(FileContent {
+            content: normalize_text(content.into_owned(), options),
+            is_binary: false,
+            is_text: true,
+            hash: None,
+            encoding: Some(encoding.name()),
+            is_base64: false,
+            skip_reason: None,
+        });
+    }
+
+    let content = match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(e) => {
+            check_utf8_policy(path, options)?;
+            if options.utf8_policy == Utf8Policy::TreatInvalidAsBinary {
+                return Ok(FileContent::binary(e.as_bytes(), options));
+            }
+            String::from_utf8_lossy(e.as_bytes()).into_owned()
+        }
+    };
+    Ok(FileContent::text(content, options))
+}
+
+/// Fails with [`SnapcatError::InvalidUtf8`] for a file with invalid text under
+/// [`Utf8Policy::Strict`].
+fn check_utf8_policy(path: &Path, options: &SnapcatOptions) -> Result<(), SnapcatError> {
+    if options.utf8_policy == Utf8Policy::Strict {
+        return Err(SnapcatError::InvalidUtf8(path.to_path_buf()));
+    }
+    Ok(())
+}
+
+/// Applies `normalize_line_endings` and `expand_tabs` to decoded text.
+fn normalize_text(text: String, options: &SnapcatOptions) -> String {
+    let text = normalize_line_endings(text, options.normalize_line_endings);
+    match options.expand_tabs {
+        Some(width) if text.contains('\t') => text.replace('\t', &" ".repeat(width)),
+        _ => text,
+    }
+}
+
+/// Rewrites every `\r\n` and `\n` line break in `text` to `ending`; `None` keeps the
+/// text unchanged.
+fn normalize_line_endings(text: String, ending: Option<LineEnding>) -> String {
+    match ending {
+        None => text,
+        Some(LineEnding::Lf) if !text.contains("\r\n") => text,
+        Some(LineEnding::Lf) => text.replace("\r\n", "\n"),
+        Some(LineEnding::Crlf) => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+    }
+}
+
+/// Picks the charset of a file: its byte order mark, UTF-8 if the bytes are valid UTF-8,
+/// or otherwise a guess from the sniff buffer.
+#[cfg(feature = "encoding")]
+fn detect_encoding(sample: &[u8], bytes: &[u8]) -> &'static encoding_rs::Encoding {
+    if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(bytes) {
+        return encoding;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return encoding_rs::UTF_8;
+    }
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(sample, true);
+    detector.guess(None, true)
+}
+
+/// Resolves `options.explicit_paths` against the root, if set, keeping only existing files.
+///
+/// Paths that are symbolic links are recorded in `cache`.
+fn explicit_paths(
+    options: &SnapcatOptions,
+    cache: &PathCache,
+) -> Option<impl Iterator<Item = PathBuf> + use<>> {
+    let root = options.root.clone();
+    let paths = options.explicit_paths.clone()?;
+    let cache = cache.clone();
+    Some(
+        paths
+            .into_iter()
+            .map(move |p| root.join(p))
+            .filter(move |p| match fs::symlink_metadata(p) {
+                Ok(m) if m.file_type().is_symlink() => {
+                    cache.record_symlink(p.clone());
+                    p.is_file()
+                }
+                Ok(m) => m.is_file(),
+                Err(_) => false,
+            }),
+    )
+}
+
+/// The outcome of walking the tree: the file paths, and the paths the walker skipped (only
+/// recorded when `collect_skip_reasons` is set).
+struct Walked {
+    file_paths: Vec<PathBuf>,
+    /// All walked entries, kept to render the tree later with `tree_annotate_first_line`.
+    tree_entries: Vec<PathBuf>,
+    skipped: Vec<(PathBuf, SkipReason)>,
+    /// Paths excluded by `.gitignore` files (only with `report_gitignore_exclusions`).
+    gitignored: Vec<PathBuf>,
+    /// The symlinks among the walked paths and the metadata fetched to sort them.
+    cache: PathCache,
+}
+
+/// Walks the tree for the given options, rendering it into `tree`.
+fn walk_files(options: &SnapcatOptions, tree: &mut String) -> Result<Walked, SnapcatError> {
+    let cache = PathCache::default();
+    let (all_entries, skipped, gitignored) = match explicit_paths(options, &cache) {
+        Some(paths) => (paths.collect(), Vec::new(), Vec::new()),
+        None => {
+            let mut walker = Walker::new(options)?;
+            walker.cache = cache.clone();
+            let log = walker.skipped.clone();
+            let gitignored = walker.gitignored.clone();
+            let entries = walker.collect_entries()?;
+            let skipped = log.map_or_else(Vec::new, |log| {
+                std::mem::take(&mut *log.lock().unwrap_or_else(|e| e.into_inner()))
+            });
+            let gitignored = gitignored.map_or_else(Vec::new, |log| {
+                std::mem::take(&mut *log.lock().unwrap_or_else(|e| e.into_inner()))
+            });
+            (entries, skipped, gitignored)
+        }
+    };
+    // With `tree_annotate_first_line`, the tree is rendered once the files are read.
+    let tree_entries = if options.tree_annotate_first_line {
+        all_entries.clone()
+    } else {
+        let roots: Vec<&Path> = options.roots().collect();
+        build_tree_from_entries(
+            tree,
+            &roots,
+            &all_entries,
+            options,
+            tree_entry_kind(options),
+            |_| None,
+        )?;
+        Vec::new()
+    };
+
+    let mut file_paths: Vec<PathBuf> = all_entries.into_iter().filter(|p| p.is_file()).collect();
+    let mut skipped = skipped;
+    sort_files(&mut file_paths, options.sort_order, |path| cache.size(path));
+    let over_limit = apply_max_files(&mut file_paths, options);
+    if options.collect_skip_reasons {
+        skipped.extend(over_limit.into_iter().map(|p| (p, SkipReason::FileLimit)));
+    }
+    let skipped = skipped
+        .into_iter()
+        .map(|(path, reason)| (entry_path(path, options), reason))
+        .collect();
+    Ok(Walked {
+        file_paths,
+        tree_entries,
+        skipped,
+        gitignored: gitignored
+            .into_iter()
+            .map(|path| entry_path(path, options))
+            .collect(),
+        cache,
+    })
+}
+
+/// Returns how tree entries are told apart: kinds are only inspected in detail for colors.
+fn tree_entry_kind(options: &SnapcatOptions) -> fn(&Path) -> EntryKind {
+    if options.color_tree {
+        EntryKind::detailed
+    } else {
+        EntryKind::plain
+    }
+}
+
+/// Renders the tree of `result` from `entries`, following each text file read in full with
+/// its first non-empty line, for `tree_annotate_first_line`.
+fn render_annotated_tree(
+    result: &mut SnapcatResult,
+    roots: &[&Path],
+    entries: &[PathBuf],
+    options: &SnapcatOptions,
+    kind: impl Fn(&Path) -> EntryKind,
+) -> Result<(), SnapcatError> {
+    let first_lines: HashMap<&Path, &str> = result
+        .files
+        .iter()
+        .filter(|file| !file.is_binary && !file.content_omitted)
+        .filter_map(|file| {
+            let line = file
+                .content
+                .lines()
+                .map(str::trim)
+                .find(|l| !l.is_empty())?;
+            Some((file.path.as_path(), line))
+        })
+        .collect();
+    build_tree_from_entries(&mut result.tree, roots, entries, options, kind, |path| {
+        let path = entry_path(path.to_path_buf(), options);
+        first_lines.get(path.as_path()).map(|line| line.to_string())
+    })
+}
+
+/// Sorts the file paths by `order`; [`SortOrder::WalkOrder`] leaves them as they are.
+///
+/// `size_of` returns the size of a file for [`SortOrder::SizeDesc`].
+fn sort_files(file_paths: &mut [PathBuf], order: SortOrder, size_of: impl Fn(&Path) -> u64) {
+    match order {
+        SortOrder::WalkOrder => {}
+        SortOrder::PathAsc => file_paths.sort(),
+        SortOrder::SizeDesc => {
+            file_paths.sort_by_cached_key(|path| (Reverse(size_of(path)), path.clone()))
+        }
+        SortOrder::ExtensionThenName => file_paths.sort_by_cached_key(|path| {
+            let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+            (extension, path.clone())
+        }),
+    }
+}
+
+/// Applies `max_files`: removes the file paths past the limit, returning them.
+///
+/// Paths in walk order are sorted lexicographically first so the selection is stable.
+fn apply_max_files(file_paths: &mut Vec<PathBuf>, options: &SnapcatOptions) -> Vec<PathBuf> {
+    let Some(max) = options.max_files else {
+        return Vec::new();
+    };
+    if options.sort_order == SortOrder::WalkOrder {
+        file_paths.sort();
+    }
+    file_paths.split_off(max.min(file_paths.len()))
+}
+
+/// Walks `root` and `extra_roots` with the same filtering as [`snapcat`], without reading
+/// any file.
+///
+/// Yields every kept directory and file, starting with the root itself, in walk order:
+/// `.gitignore` and custom ignore files, ignore and include patterns, extension filters,
+/// hidden entries, `max_depth`, `max_path_length` and symlink handling all apply.
+/// Options that only affect file processing (`explicit_paths`, `max_files`, size limits,
+/// content options) do not; skip reasons are not recorded.
+///
+/// # Errors
+///
+/// Returns an error if the walker cannot be created (e.g., invalid glob patterns); each
+/// item is an error if the walk fails at that point, as with [`WalkErrorPolicy::Fail`].
+///
+/// # Example
+///
+/// ```
+/// use snapcat::{SnapcatBuilder, walk};
+///
+/// let options = SnapcatBuilder::new(".").build();
+/// for path in walk(&options).expect("invalid options") {
+///     println!("{}", path.expect("walk failed").display());
+/// }
+/// ```
+pub fn walk(
+    options: &SnapcatOptions,
+) -> Result<impl Iterator<Item = Result<PathBuf, SnapcatError>> + use<>, SnapcatError> {
+    Ok(Walker::new(options)?.into_iter())
+}
+
+/// Main entry point for a snapcat operation.
+///
+/// This function walks the directory tree starting at `options.root`, collects all files,
+/// reads their contents (subject to size limits and binary detection), and returns a
+/// [`SnapcatResult`] containing the tree representation and file entries.
+///
+/// # Errors
+///
+/// Returns an error if the directory walk fails, if file I/O fails, or if glob patterns are invalid.
+/// With [`ErrorMode::Collect`], file I/O failures are recorded in [`SnapcatResult::errors`]
+/// instead.
+///
+/// # Example
+///
+/// ```
+/// use snapcat::{SnapcatBuilder, snapcat};
+///
+/// let options = SnapcatBuilder::new(".").build();
+/// let result = snapcat(options).expect("snapcat failed");
+/// println!("{}", result.tree);
+/// ```
+pub fn snapcat(options: SnapcatOptions) -> Result<SnapcatResult, SnapcatError> {
+    let mut result = SnapcatResult::default();
+    snapcat_into(options, &mut result)?;
+    Ok(result)
+}
+
+/// Same as [`snapcat`], but writes into an existing [`SnapcatResult`], reusing the
+/// capacity of its `tree` and `files`.
+///
+/// This is a performance-oriented alternative for callers that scan repeatedly, e.g. in a
+/// watch loop: previous contents of `result` are replaced. If an error is returned,
+/// `result` may hold partial data from this run.
+///
+/// # Errors
+///
+/// Same as [`snapcat`].
+///
+/// # Example
+///
+/// ```
+/// use snapcat::{SnapcatBuilder, SnapcatResult, snapcat_into};
+///
+/// let mut result = SnapcatResult::default();
+/// for root in ["src", "tests"] {
+///     snapcat_into(SnapcatBuilder::new(root).build(), &mut result).expect("snapcat failed");
+///     println!("{}: {} files", root, result.files.len());
+/// }
+/// ```
+pub fn snapcat_into(
+    options: SnapcatOptions,
+    result: &mut SnapcatResult,
+) -> Result<(), SnapcatError> {
+    #[cfg(feature = "logging")]
+    tracing::debug!("Starting snapcat with root: {}", options.root.display());
+
+    let started = Instant::now();
+    let filter = ContentFilter::new(&options)?;
+    walk_started(&options);
+    #[cfg(any(feature = "archive", feature = "git"))]
+    if let Some(members) = read_members(&options, &filter)? {
+        snapcat_members(members, &options, &filter, result)?;
+        record_durations(result, started, None);
+        return Ok(());
+    }
+    let walked = walk_files(&options, &mut result.tree)?;
+    let walk_finished = Instant::now();
+    let file_paths = walked.file_paths;
+    let progress = Progress::new(&options, file_paths.len());
+
+    #[cfg(not(feature = "parallel"))]
+    let processed = process_files(file_paths, &walked.cache, &options, &filter, &progress)?;
+    #[cfg(feature = "parallel")]
+    let processed =
+        process_files_parallel(file_paths, &walked.cache, &options, &filter, &progress)?;
+
+    progress.emit(ProgressEvent::Finished);
+    assemble_result(result, walked.skipped, processed, &options);
+    result.gitignored = walked.gitignored;
+    if options.tree_annotate_first_line {
+        let roots: Vec<&Path> = options.roots().collect();
+        render_annotated_tree(
+            result,
+            &roots,
+            &walked.tree_entries,
+            &options,
+            tree_entry_kind(&options),
+        )?;
+    }
+    record_durations(result, started, Some(walk_finished));
+    Ok(())
+}
+
+/// Records how long the scan begun at `started` took, split into walking and reading
+/// when the end of the walk is known.
+fn record_durations(result: &mut SnapcatResult, started: Instant, walk_finished: Option<Instant>) {
+    let now = Instant::now();
+    result.duration = Some(now - started);
+    result.walk_duration = walk_finished.map(|finished| finished - started);
+    result.read_duration = walk_finished.map(|finished| now - finished);
+}
+
+/// A file or directory of an archive or Git revision, with its content loaded in memory.
+#[cfg(any(feature = "archive", feature = "git"))]
+pub(crate) struct ArchiveMember {
+    /// Path of the member inside the archive, made of normal components only.
+    pub(crate) path: PathBuf,
+    /// Whether the member is a directory.
+    pub(crate) is_dir: bool,
+    /// Modification time recorded in the archive; zip members carry none.
+    pub(crate) modified: Option<SystemTime>,
+    /// Size of the member's content in bytes (0 for directories).
+    pub(crate) size: u64,
+    /// Raw bytes of the member: empty for directories, and only a sample for files over
+    /// their size limit.
+    pub(crate) bytes: Vec<u8>,
+}
+
+/// Reads the members of the archive or Git revision to snapshot instead of walking `root`;
+/// returns `None` for a directory source.
+///
+/// Archive members over their size limit are not read past the binary detection sample.
+#[cfg(any(feature = "archive", feature = "git"))]
+fn read_members(
+    options: &SnapcatOptions,
+    filter: &ContentFilter,
+) -> Result<Option<Vec<ArchiveMember>>, SnapcatError> {
+    #[cfg(not(feature = "archive"))]
+    let _ = filter;
+    match options.source() {
+        Source::Directory(_) => Ok(None),
+        #[cfg(feature = "archive")]
+        Source::Archive(archive) => {
+            let size_limit = |member: &Path| filter.size_limit(&options.root.join(member), options);
+            crate::archive::read_archive(&archive, &size_limit, options.binary_sample_size)
+                .map(Some)
+        }
+        #[cfg(feature = "git")]
+        Source::Git { repo, rev } => crate::git::read_revision(&repo, &rev).map(Some),
+    }
+}
+
+/// Snapshots the members of an archive or Git revision into `result`.
+///
+/// Members are placed under `root` as a virtual root and filtered like walked entries; a
+/// rejected directory hides everything below it. Files are then processed from memory in
+/// order.
+#[cfg(any(feature = "archive", feature = "git"))]
+fn snapcat_members(
+    members: Vec<ArchiveMember>,
+    options: &SnapcatOptions,
+    filter: &ContentFilter,
+    result: &mut SnapcatResult,
+) -> Result<(), SnapcatError> {
+    let root = &options.root;
+    let entry_filter = EntryFilter::new(options, !options.include_hidden)?;
+    let rejected = |member: &Path, is_dir: bool| {
+        let depth = member.components().count();
+        entry_filter.check(&root.join(member), is_dir, depth)
+    };
+
+    let mut skipped = Vec::new();
+    let mut entries = Vec::new();
+    let mut dirs = HashSet::new();
+    let mut files = HashMap::new();
+    for member in members {
+        let depth = member.path.components().count();
+        let hidden_by_parent = member
+            .path
+            .ancestors()
+            .skip(1)
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .any(|parent| rejected(parent, true).is_some());
+        if hidden_by_parent || walk_depth(options).is_some_and(|max| depth > max) {
+            continue;
+        }
+        let path = root.join(&member.path);
+        if let Some(reason) = rejected(&member.path, member.is_dir) {
+            if options.collect_skip_reasons {
+                skipped.push((path, reason));
+            }
+            continue;
+        }
+        if member.is_dir {
+            dirs.insert(path.clone());
+        } else if files.insert(path.clone(), member).is_some() {
+            // A later member with the same path replaces the earlier one.
+            continue;
+        }
+        entries.push(path);
+    }
+    let kind = |path: &Path| {
+        if dirs.contains(path) {
+            EntryKind::Dir
+        } else {
+            EntryKind::File
+        }
+    };
+    let tree_entries = if options.tree_annotate_first_line {
+        entries.clone()
+    } else {
+        build_tree_from_entries(
+            &mut result.tree,
+            &[root.as_path()],
+            &entries,
+            options,
+            kind,
+            |_| None,
+        )?;
+        Vec::new()
+    };
+
+    let mut file_paths: Vec<PathBuf> = entries
+        .into_iter()
+        .filter(|path| files.contains_key(path))
+        .collect();
+    sort_files(&mut file_paths, options.sort_order, |path| {
+        files.get(path).map_or(0, |m| m.size)
+    });
+    let over_limit = apply_max_files(&mut file_paths, options);
+    if options.collect_skip_reasons {
+        skipped.extend(over_limit.into_iter().map(|p| (p, SkipReason::FileLimit)));
+    }
+    let skipped = skipped
+        .into_iter()
+        .map(|(path, reason)| (entry_path(path, options), reason))
+        .collect();
+
+    let progress = Progress::new(options, file_paths.len());
+    let mut budget = ContentBudget::new(options);
+    let mut processed = Vec::with_capacity(file_paths.len());
+    for path in file_paths {
+        let Some(member) = files.remove(&path) else {
+            continue;
+        };
+        let stat = Some(FileStat {
+            len: member.size,
+            modified: member.modified,
+            mode: None,
+        });
+        let outcome = if is_filtered_out(stat, options) {
+            Ok(FileOutcome::default())
+        } else {
+            let read = if !options.read_content {
+                let sample = &member.bytes[..member.bytes.len().min(options.binary_sample_size)];
+                if options.sniff_binary {
+                    Ok(FileContent::sniffed(&path, sample, options))
+                } else {
+                    Ok(FileContent::not_read(&path))
+                }
+            } else if budget.admit(stat) {
+                let size_limit = filter.size_limit(&path, options);
+                if size_limit.is_some_and(|limit| member.size > limit) {
+                    // Only a sample of the member was read, so it is not hashed either.
+                    Ok(FileContent::too_large(options))
+                } else {
+                    content_from_bytes(&path, member.bytes, size_limit, options)
+                }
+            } else {
+                Ok(FileContent::omitted(BUDGET_PLACEHOLDER, false))
+            };
+            read.map(|read| into_outcome(path.clone(), read, stat, options, filter))
+        };
+        processed.push(finish_file(path, outcome, options, filter, &progress)?);
+    }
+    progress.emit(ProgressEvent::Finished);
+    assemble_result(result, skipped, processed, options);
+    if options.tree_annotate_first_line {
+        render_annotated_tree(result, &[root.as_path()], &tree_entries, options, kind)?;
+    }
+    Ok(())
+}
+
+/// The outcome of reading a single file.
+#[derive(Default)]
+struct FileOutcome {
+    /// The entry, unless the file is excluded from the results.
+    entry: Option<FileEntry>,
+    /// Why the file was skipped or its content omitted, if tracked by [`SkipReason`].
+    skip_reason: Option<SkipReason>,
+}
+
+impl FileOutcome {
+    /// Marks the entry as a symlink to `target`, if it is one.
+    fn with_symlink_target(mut self, target: Option<PathBuf>) -> Self {
+        if let Some(entry) = self.entry.as_mut() {
+            entry.is_symlink = target.is_some();
+            entry.symlink_target = target;
+        }
+        self
+    }
+}
+
+/// A processed file: its entry (unless skipped), the error recorded for it, and its skip
+/// reason, if any.
+struct ProcessedFile {
+    entry: Option<FileEntry>,
+    error: Option<SnapcatError>,
+    skipped: Option<(PathBuf, SkipReason)>,
+}
+
+/// Splits processed entries into `result`, whose tree has already been rendered.
+///
+/// `skipped` holds the paths already skipped by the walker.
+fn assemble_result(
+    result: &mut SnapcatResult,
+    mut skipped: Vec<(PathBuf, SkipReason)>,
+    processed: Vec<ProcessedFile>,
+    options: &SnapcatOptions,
+) {
+    result.root.clone_from(&options.root);
+    result.files.clear();
+    result.files.reserve(processed.len());
+    result.errors.clear();
+    result.gitignored.clear();
+    let mut dedup = ContentDedup::new(options);
+    for file in processed {
+        result.files.extend(file.entry.map(|mut entry| {
+            dedup.apply(&mut entry);
+            entry
+        }));
+        result.errors.extend(file.error);
+        skipped.extend(file.skipped);
+    }
+    result.skipped = options.collect_skip_reasons.then_some(skipped);
+}
+
+/// Reads a single file and assembles its [`FileEntry`] according to the options.
+///
+/// Returns `None` for files excluded from the results (see [`process_stated_file`]).
+#[cfg(any(not(feature = "parallel"), feature = "streaming"))]
+fn process_file(
+    path: PathBuf,
+    cache: &PathCache,
+    options: &SnapcatOptions,
+    filter: &ContentFilter,
+    budget: &mut ContentBudget,
+) -> Result<FileOutcome, SnapcatError> {
+    let metadata = cache.stat(&path, options)?;
+    if is_filtered_out(metadata, options) {
+        return Ok(FileOutcome::default());
+    }
+    let admitted = budget.admit(metadata);
+    process_stated_file(path, metadata, admitted, cache, options, filter)
+}
+
+/// Reads a file whose metadata was already fetched and assembles its [`FileEntry`].
+///
+/// Files not `admitted` by the [`ContentBudget`] are listed without reading their content.
+/// Returns `None` for files excluded by their metadata (e.g. `min_file_size`) or by
+/// [`BinaryHandling::Skip`].
+fn process_stated_file(
+    path: PathBuf,
+    metadata: Option<FileStat>,
+    admitted: bool,
+    cache: &PathCache,
+    options: &SnapcatOptions,
+    filter: &ContentFilter,
+) -> Result<FileOutcome, SnapcatError> {
+    if is_filtered_out(metadata, options) {
+        return Ok(FileOutcome::default());
+    }
+    let link = cache.symlink_target(&path);
+    let read = if !options.read_content {
+        unread_file_content(&path, options)?
+    } else if link.is_some() && options.follow_links != FollowLinks::All {
+        FileContent::omitted(SYMLINK_PLACEHOLDER, false)
+    } else if admitted {
+        read_file_content(&path, metadata, filter.size_limit(&path, options), options)?
+    } else {
+        FileContent::omitted(BUDGET_PLACEHOLDER, false)
+    };
+    Ok(into_outcome(path, read, metadata, options, filter).with_symlink_target(link))
+}
+
+/// Returns whether a file is excluded from the results based on its metadata alone.
+///
+/// Files whose modification time cannot be read are kept.
+fn is_filtered_out(metadata: Option<FileStat>, options: &SnapcatOptions) -> bool {
+    let Some(metadata) = metadata else {
+        return false;
+    };
+    if options.min_file_size.is_some_and(|min| metadata.len < min) {
+        return true;
+    }
+    if options.modified_after.is_none() && options.modified_before.is_none() {
+        return false;
+    }
+    let Some(modified) = metadata.modified else {
+        return false;
+    };
+    options
+        .modified_after
+        .is_some_and(|after| modified <= after)
+        || options
+            .modified_before
+            .is_some_and(|before| modified >= before)
+}
+
+/// Builds the entry for a read file, dropping it if [`BinaryHandling::Skip`] applies.
+fn into_outcome(
+    path: PathBuf,
+    read: FileContent,
+    metadata: Option<FileStat>,
+    options: &SnapcatOptions,
+    filter: &ContentFilter,
+) -> FileOutcome {
+    let skip_reason = read.skip_reason;
+    let entry = build_entry(path, read, metadata, options, filter);
+    let skipped = entry.is_binary && options.binary_handling == BinaryHandling::Skip;
+    FileOutcome {
+        entry: (!skipped).then_some(entry),
+        skip_reason,
+    }
+}
+
+/// The compiled `content_matches` and redaction patterns and `line_ranges` and
+/// `size_limit_overrides` globs, built once per run.
+struct ContentFilter {
+    pattern: Option<regex::Regex>,
+    redactions: Vec<regex::Regex>,
+    line_ranges: Option<PathGlobs<(usize, usize)>>,
+    size_limits: Option<PathGlobs<u64>>,
+}
+
+impl ContentFilter {
+    /// Compiles the patterns and the line range and size limit globs from the options, if
+    /// any.
+    fn new(options: &SnapcatOptions) -> Result<Self, SnapcatError> {
+        let pattern = options
+            .content_matches
+            .as_deref()
+            .map(regex::Regex::new)
+            .transpose()
+            .map_err(|e| SnapcatError::Config(format!("Invalid content pattern: {}", e)))?;
+        let defaults = if options.redact_defaults {
+            DEFAULT_REDACT_PATTERNS
+        } else {
+            &[]
+        };
+        let redactions = defaults
+            .iter()
+            .copied()
+            .chain(options.redact_patterns.iter().map(String::as_str))
+            .map(|pattern| {
+                regex::Regex::new(pattern).map_err(|e| {
+                    SnapcatError::Config(format!("Invalid redact pattern '{}': {}", pattern, e))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            pattern,
+            redactions,
+            line_ranges: PathGlobs::new(&options.line_ranges, options)?,
+            size_limits: PathGlobs::new(&options.size_limit_overrides, options)?,
+        })
+    }
+
+    /// Returns the size limit of a file: that of the first `size_limit_overrides` glob
+    /// matching it, or else `file_size_limit`.
+    fn size_limit(&self, path: &Path, options: &SnapcatOptions) -> Option<u64> {
+        self.size_limits
+            .as_ref()
+            .and_then(|limits| limits.get(path))
+            .copied()
+            .or(options.file_size_limit)
+    }
+
+    /// Drops the entry unless its content matches; omitted or base64 content never
+    /// matches.
+    ///
+    /// Kept entries are then cut down to their line range, if one applies.
+    fn apply(&self, mut outcome: FileOutcome) -> FileOutcome {
+        if let Some(regex) = &self.pattern {
+            outcome.entry = outcome.entry.filter(|entry| {
+                !entry.content_omitted && !entry.is_base64 && regex.is_match(&entry.content)
+            });
+        }
+        if let (Some(ranges), Some(entry)) = (&self.line_ranges, outcome.entry.as_mut()) {
+            apply_line_range(ranges, entry);
+        }
+        outcome
+    }
+
+    /// Replaces the matches of the redaction patterns in decoded text with
+    /// [`REDACTED_MARKER`]; in patterns with a `secret` group, only that group is replaced.
+    fn redact(&self, mut content: String) -> String {
+        for regex in &self.redactions {
+            let replaced = regex.replace_all(&content, |caps: &regex::Captures<'_>| {
+                let whole = &caps[0];
+                match caps.name("secret") {
+                    Some(secret) => {
+                        let start = secret.start() - caps.get(0).map_or(0, |m| m.start());
+                        let end = start + secret.len();
+                        format!("{}{}{}", &whole[..start], REDACTED_MARKER, &whole[end..])
+                    }
+                    None => REDACTED_MARKER.to_string(),
+                }
+            });
+            if let std::borrow::Cow::Owned(redacted) = replaced {
+                content = redacted;
+            }
+        }
+        content
+    }
+}
+
+/// Text replacing redacted secrets.
+const REDACTED_MARKER: &str = "[REDACTED]";
+
+/// Patterns for common secrets, used with `redact_defaults`.
+const DEFAULT_REDACT_PATTERNS: &[&str] = &[
+    // AWS access key IDs.
+    r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b",
+    // GitHub tokens.
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}\b",
+    // PEM private keys.
+    r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+    // Assignments such as `api_key = ...`, `token: ...` or `AWS_SECRET_ACCESS_KEY=...`.
+    r#"(?i)\b[a-z0-9_.-]*(?:api[_-]?key|token|secret|password|passwd)[a-z0-9_]*["']?\s*[:=]\s*["']?(?P<secret>[^\s"',;]+)"#,
+];
+
+/// Compiled globs mapped to values, such as the `line_ranges` of the options.
+struct PathGlobs<T> {
+    globs: globset::GlobSet,
+    values: Vec<T>,
+    roots: Vec<PathBuf>,
+}
+
+impl<T: Clone> PathGlobs<T> {
+    /// Compiles the globs, returning `None` when the list is empty.
+    fn new(
+        entries: &[(String, T)],
+        options: &SnapcatOptions,
+    ) -> Result<Option<Self>, SnapcatError> {
+        let (patterns, values): (Vec<String>, Vec<T>) = entries.iter().cloned().unzip();
+        Ok(
+            build_glob_set(&patterns, options.case_insensitive_globs)?.map(|globs| Self {
+                globs,
+                values,
+                roots: options.roots().map(Path::to_path_buf).collect(),
+            }),
+        )
+    }
+
+    /// Returns the value of the first pattern matching the path, either as given or
+    /// relative to its root.
+    fn get(&self, path: &Path) -> Option<&T> {
+        let relative = self
+            .roots
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok());
+        std::iter::once(path)
+            .chain(relative)
+            .flat_map(|p| self.globs.matches(p))
+            .min()
+            .map(|i| &self.values[i])
+    }
+}
+
+/// Reduces the content of a text entry to its line range, if one applies.
+fn apply_line_range(ranges: &PathGlobs<(usize, usize)>, entry: &mut FileEntry) {
+    if entry.content_omitted || entry.is_base64 {
+        return;
+    }
+    let Some(&(start, end)) = ranges.get(&entry.path) else {
+        return;
+    };
+    let (content, range) = extract_lines(&entry.content, start, end);
+    entry.content = content;
+    entry.line_range = range;
+    if entry.token_estimate.is_some() {
+        entry.token_estimate = Some(estimate_tokens(&entry.content));
+    }
+}
+
+/// Keeps lines `start..=end` (1-based) of `text`, replacing the lines before and after with
+/// omission markers.
+///
+/// Returns the new text and the range actually kept, clamped to the number of lines;
+/// `None` if the range starts past the last line, in which case only a marker remains.
+fn extract_lines(text: &str, start: usize, end: usize) -> (String, Option<(usize, usize)>) {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let total = lines.len();
+    let start = start.max(1);
+    let end = end.min(total);
+    let mut out = String::new();
+    if start > end {
+        if total > 0 {
+            out.push_str(&omitted_lines(1, total));
+        }
+        return (out, None);
+    }
+    if start > 1 {
+        out.push_str(&omitted_lines(1, start - 1));
+    }
+    for line in &lines[start - 1..end] {
+        out.push_str(line);
+    }
+    if end < total {
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(&omitted_lines(end + 1, total));
+    }
+    (out, Some((start, end)))
+}
+
+/// Marker line standing in for lines `from..=to` left out by [`extract_lines`]
+fn omitted_lines(from: usize, to: usize) -> String {
+    if from == to {
+        format!("[... line {} omitted ...]\n", from)
+    } else {
+        format!("[... lines {}-{} omitted ...]\n", from, to)
+    }
+}
+
+/// Replaces the content of files identical to an earlier one with a reference to it.
+///
+/// Inactive unless the `hash` feature is enabled and `dedup_content` is set.
+struct ContentDedup {
+    /// First path seen for each content hash.
+    #[cfg(feature = "hash")]
+    seen: Option<HashMap<String, PathBuf>>,
+    /// Whether hashes stay in the entries (`include_hash`) or were computed only for dedup.
+    #[cfg(feature = "hash")]
+    keep_hash: bool,
+}
+
+impl ContentDedup {
+    /// Creates a deduplicator that is active only if the options request it.
+    #[cfg_attr(not(feature = "hash"), allow(unused_variables))]
+    fn new(options: &SnapcatOptions) -> Self {
+        Self {
+            #[cfg(feature = "hash")]
+            seen: options.dedup_content.then(HashMap::new),
+            #[cfg(feature = "hash")]
+            keep_hash: options.include_hash,
+        }
+    }
+
+    /// Creates a deduplicator that leaves every entry as is.
+    #[cfg(all(feature = "streaming", any(feature = "archive", feature = "git")))]
+    fn disabled() -> Self {
+        Self {
+            #[cfg(feature = "hash")]
+            seen: None,
+            #[cfg(feature = "hash")]
+            keep_hash: true,
+        }
+    }
 
-        if let Some(ref matcher) = matcher {
-            let matcher = matcher.clone();
-            builder.filter_entry(move |entry| !matcher.is_match(entry.path()));
+    /// Records the entry's hash, turning the entry into a reference if the hash was seen
+    /// before.
+    #[cfg_attr(not(feature = "hash"), allow(unused_variables))]
+    fn apply(&mut self, entry: &mut FileEntry) {
+        #[cfg(feature = "hash")]
+        if let Some(seen) = self.seen.as_mut() {
+            let Some(hash) = entry.hash.as_ref() else {
+                return;
+            };
+            match seen.get(hash) {
+                Some(original) => {
+                    entry.content = format!("[Duplicate of {}]", original.display());
+                    entry.content_omitted = true;
+                    entry.is_base64 = false;
+                    entry.truncated = false;
+                    entry.token_estimate = None;
+                    entry.duplicate_of = Some(original.clone());
+                }
+                None => {
+                    seen.insert(hash.clone(), entry.path.clone());
+                }
+            }
+            if !self.keep_hash {
+                entry.hash = None;
+            }
         }
+    }
+}
 
-        Ok(Self {
-            inner: builder.build(),
-            matcher,
-        })
+/// Tracks the cumulative size of file contents read against `max_total_content_bytes`.
+struct ContentBudget {
+    remaining: Option<u64>,
+    exhausted: bool,
+}
+
+impl ContentBudget {
+    /// Creates a budget from the options; unlimited if no maximum is set.
+    fn new(options: &SnapcatOptions) -> Self {
+        Self {
+            remaining: options.max_total_content_bytes,
+            exhausted: false,
+        }
     }
 
-    /// Converts the walker into an iterator over paths.
-    fn into_iter(self) -> impl Iterator<Item = Result<PathBuf, SnapcatError>> {
-        self.inner.filter_map(|result| match result {
-            Ok(entry) => Some(Ok(entry.path().to_path_buf())),
-            Err(e) => Some(Err(SnapcatError::Walk(e.to_string()))),
-        })
+    /// Returns whether a file may still have its content read, charging its size if so.
+    ///
+    /// Once a file does not fit, every later file is refused as well.
+    fn admit(&mut self, metadata: Option<FileStat>) -> bool {
+        let Some(remaining) = self.remaining.as_mut() else {
+            return true;
+        };
+        let len = metadata.map_or(0, |m| m.len);
+        if self.exhausted || len > *remaining {
+            #[cfg(feature = "logging")]
+            if !self.exhausted {
+                tracing::debug!("Total content budget reached, omitting remaining contents");
+            }
+            self.exhausted = true;
+            return false;
+        }
+        *remaining -= len;
+        true
     }
 
-    /// Collects all paths into a Vec.
-    fn collect_entries(self) -> Result<Vec<PathBuf>, SnapcatError> {
-        self.into_iter().collect()
+    /// Decides admission for already-stated files in order; failed stats and filtered-out
+    /// files are not charged.
+    #[cfg(any(feature = "parallel", feature = "async"))]
+    fn admit_all(
+        &mut self,
+        stats: &[Result<Option<FileStat>, SnapcatError>],
+        options: &SnapcatOptions,
+    ) -> Vec<bool> {
+        stats
+            .iter()
+            .map(|stat| match stat {
+                Ok(metadata) if !is_filtered_out(*metadata, options) => self.admit(*metadata),
+                _ => true,
+            })
+            .collect()
     }
 }
 
-/// Reads a file's content with binary detection and size limit.
-///
-/// Returns a tuple `(content, is_binary)`.
-fn read_file_content(
-    path: &Path,
-    binary_detection: BinaryDetection,
-    size_limit: Option<u64>,
-) -> Result<(String, bool), SnapcatError> {
-    if let Some(limit) = size_limit {
-        let metadata = fs::metadata(path).map_err(|e| SnapcatError::io(path, e))?;
-        if metadata.len() > limit {
-            #[cfg(feature = "logging")]
-            tracing::debug!(
-                "File too large ({} > {}), skipping content",
-                metadata.len(),
-                limit
-            );
-            return Ok(("[File too large, content omitted]".to_string(), false));
+/// The parts of a file's metadata that entries and filters use.
+#[derive(Debug, Clone, Copy)]
+struct FileStat {
+    len: u64,
+    modified: Option<SystemTime>,
+    /// Unix permission bits; `None` elsewhere and for archive members.
+    mode: Option<u32>,
+}
+
+impl From<fs::Metadata> for FileStat {
+    fn from(metadata: fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let mode = Some(std::os::unix::fs::PermissionsExt::mode(
+            &metadata.permissions(),
+        ));
+        #[cfg(not(unix))]
+        let mode = None;
+        Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            mode,
         }
     }
+}
 
-    let file = File::open(path).map_err(|e| SnapcatError::io(path, e))?;
-    let mut reader = BufReader::new(file);
+/// Stats a file once if any option needs its metadata (size limits, size, or mtime).
+fn stat_if_needed(path: &Path, options: &SnapcatOptions) -> Result<Option<FileStat>, SnapcatError> {
+    if needs_metadata(options) {
+        fs::metadata(path)
+            .map(|m| Some(m.into()))
+            .map_err(|e| SnapcatError::io(path, e))
+    } else {
+        Ok(None)
+    }
+}
 
-    // Read first 4KiB for binary detection
-    let mut first_chunk = Vec::with_capacity(4096);
-    let _ = reader
-        .by_ref()
-        .take(4096)
-        .read_to_end(&mut first_chunk)
-        .map_err(|e| SnapcatError::io(path, e))?;
+/// Returns whether processing a file requires its metadata.
+fn needs_metadata(options: &SnapcatOptions) -> bool {
+    options.file_size_limit.is_some()
+        || !options.size_limit_overrides.is_empty()
+        || options.min_file_size.is_some()
+        || options.modified_after.is_some()
+        || options.modified_before.is_some()
+        || options.max_total_content_bytes.is_some()
+        || options.include_file_size
+        || options.include_modified
+        || options.include_permissions
+}
 
-    let is_binary = match binary_detection {
-        BinaryDetection::Simple => first_chunk.contains(&0),
-        BinaryDetection::Accurate => content_inspector::inspect(&first_chunk).is_binary(),
-        BinaryDetection::None => false,
+/// Assembles a [`FileEntry`] from content that has already been read.
+///
+/// Text is redacted before it is counted, truncated or estimated, so a cut cannot split a
+/// secret out of reach of its pattern. `metadata` is only consulted for the fields the
+/// options ask for.
+fn build_entry(
+    path: PathBuf,
+    read: FileContent,
+    metadata: Option<FileStat>,
+    options: &SnapcatOptions,
+    filter: &ContentFilter,
+) -> FileEntry {
+    let content = if read.is_text {
+        filter.redact(read.content)
+    } else {
+        read.content
+    };
+    let line_count = if options.include_line_count && read.is_text {
+        Some(content.matches('\n').count())
+    } else {
+        None
     };
+    let (content, truncated) = if read.is_text {
+        truncate_content(content, options.max_content_bytes_per_file)
+    } else {
+        (content, false)
+    };
+    let token_estimate = if options.include_tokens && read.is_text {
+        Some(estimate_tokens(&content))
+    } else {
+        None
+    };
+    let size = metadata
+        .filter(|_| options.include_file_size)
+        .map(|m| m.len);
+    let modified = metadata
+        .filter(|_| options.include_modified)
+        .and_then(|m| m.modified);
+    #[cfg(unix)]
+    let mode = metadata
+        .filter(|_| options.include_permissions)
+        .and_then(|m| m.mode);
+    #[cfg(feature = "mime")]
+    let mime = options
+        .include_mime
+        .then(|| guess_mime(&path, read.is_binary));
+    #[cfg(not(feature = "mime"))]
+    let mime = None;
+    let language = options
+        .include_language
+        .then(|| language_for_path(&path))
+        .filter(|language| !language.is_empty())
+        .map(str::to_string);
+    FileEntry {
+        path: entry_path(path, options),
+        content,
+        is_binary: read.is_binary,
+        content_omitted: !read.is_text && !read.is_base64,
+        is_base64: read.is_base64,
+        truncated,
+        size,
+        line_count,
+        hash: read.hash,
+        is_symlink: false,
+        symlink_target: None,
+        duplicate_of: None,
+        line_range: None,
+        encoding: read.encoding.map(str::to_string),
+        mime,
+        language,
+        modified,
+        #[cfg(unix)]
+        mode,
+        token_estimate,
+    }
+}
 
-    if is_binary {
-        #[cfg(feature = "logging")]
-        tracing::debug!("Binary file detected: {}", path.display());
-        return Ok(("[Binary file, content omitted]".to_string(), true));
+/// Guesses the MIME type of a file from its extension, falling back to a generic text or
+/// binary type.
+#[cfg(feature = "mime")]
+fn guess_mime(path: &Path, is_binary: bool) -> String {
+    match mime_guess::from_path(path).first() {
+        Some(mime) => mime.essence_str().to_string(),
+        None if is_binary => "application/octet-stream".to_string(),
+        None => "text/plain".to_string(),
     }
+}
 
-    let mut content = String::from_utf8_lossy(&first_chunk).into_owned();
-    reader
-        .read_to_string(&mut content)
-        .map_err(|e| SnapcatError::io(path, e))?;
+/// Returns the path to store in a [`FileEntry`]: relative to the root if `relative_paths`
+/// is set and the path lies under it, the path as walked otherwise; minus the first
+/// `strip_components` components.
+fn entry_path(path: PathBuf, options: &SnapcatOptions) -> PathBuf {
+    let path = if options.relative_paths {
+        match path.strip_prefix(&options.root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => path,
+        }
+    } else {
+        path
+    };
+    strip_leading_components(&path, options.strip_components)
+}
 
-    Ok((content, false))
+/// Marker appended to content cut at `max_content_bytes_per_file`.
+const TRUNCATED_MARKER: &str = "… [truncated]";
+
+/// Cuts text longer than `limit` bytes at the last character boundary within the limit and
+/// appends [`TRUNCATED_MARKER`]. Returns the content and whether it was truncated.
+fn truncate_content(mut content: String, limit: Option<usize>) -> (String, bool) {
+    let Some(limit) = limit.filter(|&limit| content.len() > limit) else {
+        return (content, false);
+    };
+    let mut end = limit;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    content.truncate(end);
+    content.push_str(TRUNCATED_MARKER);
+    (content, true)
 }
 
-/// Main entry point for a snapcat operation.
-///
-/// This function walks the directory tree starting at `options.root`, collects all files,
-/// reads their contents (subject to size limits and binary detection), and returns a
-/// [`SnapcatResult`] containing the tree representation and file entries.
-///
-/// # Errors
-///
-/// Returns an error if the directory walk fails, if file I/O fails, or if glob patterns are invalid.
-///
-/// # Example
-///
-/// ```
-/// use snapcat::{SnapcatBuilder, snapcat};
+/// Estimates the number of LLM tokens in `text` using the ~4 characters per token heuristic.
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Applies the configured [`ErrorMode`] to the outcome of processing a single file and
+/// notifies the `on_file` and `progress` callbacks about the resulting entry.
 ///
-/// let options = SnapcatBuilder::new(".").build();
-/// let result = snapcat(options).expect("snapcat failed");
-/// println!("{}", result.tree);
-/// ```
-pub fn snapcat(options: SnapcatOptions) -> Result<SnapcatResult, SnapcatError> {
-    #[cfg(feature = "logging")]
-    tracing::debug!("Starting snapcat with root: {}", options.root.display());
+/// In [`ErrorMode::Collect`] a failed read yields a placeholder entry together with the error.
+/// Fails with [`SnapcatError::Cancelled`] once the `cancel` flag is set.
+fn finish_file(
+    path: PathBuf,
+    outcome: Result<FileOutcome, SnapcatError>,
+    options: &SnapcatOptions,
+    filter: &ContentFilter,
+    progress: &Progress<'_>,
+) -> Result<ProcessedFile, SnapcatError> {
+    if is_cancelled(options.cancel.as_deref()) {
+        return Err(SnapcatError::Cancelled);
+    }
+    let outcome = outcome.map(|outcome| filter.apply(outcome));
+    let processed = apply_error_mode(entry_path(path, options), outcome, options.on_error)?;
+    if let (Some(callback), Some(entry)) = (&options.on_file, &processed.entry) {
+        (callback.0)(entry);
+    }
+    progress.file_processed();
+    Ok(processed)
+}
+
+/// Reports [`ProgressEvent`]s to the configured `progress` callback, if any.
+struct Progress<'a> {
+    callback: Option<&'a ProgressCallback>,
+    total: usize,
+    /// Number of files processed so far; the lock also serializes callback invocations.
+    processed: Mutex<usize>,
+}
 
-    let walker = Walker::new(&options)?;
-    let all_entries = walker.collect_entries()?;
-    let tree = build_tree_from_entries(&options.root, &all_entries)?;
+impl<'a> Progress<'a> {
+    fn new(options: &'a SnapcatOptions, total: usize) -> Self {
+        Self {
+            callback: options.progress.as_ref(),
+            total,
+            processed: Mutex::new(0),
+        }
+    }
 
-    let file_paths: Vec<PathBuf> = all_entries.into_iter().filter(|p| p.is_file()).collect();
+    /// Emits a single event.
+    fn emit(&self, event: ProgressEvent) {
+        if let Some(callback) = self.callback {
+            (callback.0)(event);
+        }
+    }
 
-    #[cfg(not(feature = "parallel"))]
-    let files = process_files(file_paths, &options)?;
-    #[cfg(feature = "parallel")]
-    let files = process_files_parallel(file_paths, &options)?;
+    /// Emits [`ProgressEvent::FileProcessed`] for the next file.
+    fn file_processed(&self) {
+        if self.callback.is_none() {
+            return;
+        }
+        let mut processed = self.processed.lock().unwrap_or_else(|e| e.into_inner());
+        self.emit(ProgressEvent::FileProcessed {
+            index: *processed,
+            total: self.total,
+        });
+        *processed += 1;
+    }
+}
+
+/// Emits [`ProgressEvent::WalkStarted`] if a `progress` callback is configured.
+fn walk_started(options: &SnapcatOptions) {
+    if let Some(callback) = &options.progress {
+        (callback.0)(ProgressEvent::WalkStarted);
+    }
+}
 
-    Ok(SnapcatResult { tree, files })
+/// Applies the configured [`ErrorMode`] to the outcome of processing a single file.
+fn apply_error_mode(
+    path: PathBuf,
+    outcome: Result<FileOutcome, SnapcatError>,
+    mode: ErrorMode,
+) -> Result<ProcessedFile, SnapcatError> {
+    match (outcome, mode) {
+        (Ok(outcome), _) => Ok(ProcessedFile {
+            entry: outcome.entry,
+            error: None,
+            skipped: outcome.skip_reason.map(|reason| (path, reason)),
+        }),
+        (Err(e), ErrorMode::Fail) => Err(e),
+        (Err(e), ErrorMode::Collect) => {
+            #[cfg(feature = "logging")]
+            tracing::debug!("Recording error for {}: {}", path.display(), e);
+            let entry = FileEntry {
+                path,
+                content: format!("[Error reading file: {}]", e),
+                is_binary: false,
+                content_omitted: true,
+                is_base64: false,
+                truncated: false,
+                size: None,
+                line_count: None,
+                hash: None,
+                is_symlink: false,
+                symlink_target: None,
+                duplicate_of: None,
+                line_range: None,
+                encoding: None,
+                mime: None,
+                language: None,
+                modified: None,
+                #[cfg(unix)]
+                mode: None,
+                token_estimate: None,
+            };
+            Ok(ProcessedFile {
+                entry: Some(entry),
+                error: Some(e),
+                skipped: None,
+            })
+        }
+    }
 }
 
 /// Process files sequentially.
 #[cfg(not(feature = "parallel"))]
 fn process_files(
     paths: Vec<PathBuf>,
+    cache: &PathCache,
     options: &SnapcatOptions,
-) -> Result<Vec<FileEntry>, SnapcatError> {
-    let mut files = Vec::with_capacity(paths.len());
-    for path in paths {
-        let (content, is_binary) =
-            read_file_content(&path, options.binary_detection, options.file_size_limit)?;
-        let size = if options.include_file_size {
-            Some(
-                fs::metadata(&path)
-                    .map_err(|e| SnapcatError::io(&path, e))?
-                    .len(),
-            )
-        } else {
-            None
-        };
-        files.push(FileEntry {
-            path,
-            content,
-            is_binary,
-            size,
-        });
-    }
-    Ok(files)
+    filter: &ContentFilter,
+    progress: &Progress<'_>,
+) -> Result<Vec<ProcessedFile>, SnapcatError> {
+    let mut budget = ContentBudget::new(options);
+    paths
+        .into_iter()
+        .map(|path| {
+            let outcome = process_file(path.clone(), cache, options, filter, &mut budget);
+            finish_file(path, outcome, options, filter, progress)
+        })
+        .collect()
 }
 
 /// Process files in parallel using Rayon.
+///
+/// Files are stated in parallel first so the content budget can be applied in walk order,
+/// keeping the result deterministic. Runs on a dedicated pool if `thread_count` is set.
 #[cfg(feature = "parallel")]
 fn process_files_parallel(
     paths: Vec<PathBuf>,
+    cache: &PathCache,
     options: &SnapcatOptions,
-) -> Result<Vec<FileEntry>, SnapcatError> {
-    paths
-        .par_iter()
-        .map(|path| {
-            let (content, is_binary) =
-                read_file_content(path, options.binary_detection, options.file_size_limit)?;
-            let size = if options.include_file_size {
-                Some(
-                    fs::metadata(path)
-                        .map_err(|e| SnapcatError::io(path, e))?
-                        .len(),
-                )
-            } else {
-                None
-            };
-            Ok(FileEntry {
-                path: path.clone(),
-                content,
-                is_binary,
-                size,
+    filter: &ContentFilter,
+    progress: &Progress<'_>,
+) -> Result<Vec<ProcessedFile>, SnapcatError> {
+    let process = || {
+        let stats: Vec<_> = paths
+            .par_iter()
+            .map(|path| cache.stat(path, options))
+            .collect();
+        let admitted = ContentBudget::new(options).admit_all(&stats, options);
+
+        paths
+            .into_par_iter()
+            .zip(stats)
+            .zip(admitted)
+            .map(|((path, stat), admitted)| {
+                let outcome = stat.and_then(|metadata| {
+                    process_stated_file(path.clone(), metadata, admitted, cache, options, filter)
+                });
+                finish_file(path, outcome, options, filter, progress)
             })
-        })
-        .collect()
+            .collect()
+    };
+
+    match build_thread_pool(options)? {
+        Some(pool) => pool.install(process),
+        None => process(),
+    }
+}
+
+/// Builds a dedicated Rayon pool with `thread_count` threads, or `None` to use the global
+/// pool.
+#[cfg(feature = "parallel")]
+fn build_thread_pool(options: &SnapcatOptions) -> Result<Option<rayon::ThreadPool>, SnapcatError> {
+    let Some(threads) = options.thread_count else {
+        return Ok(None);
+    };
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map(Some)
+        .map_err(|e| SnapcatError::Config(format!("Failed to build thread pool: {}", e)))
 }
 
 /// A streaming iterator over file entries.
@@ -227,8 +2498,68 @@ fn process_files_parallel(
 /// Only available when the `streaming` feature is enabled.
 #[cfg(feature = "streaming")]
 pub struct SnapcatStream {
-    path_iter: Box<dyn Iterator<Item = Result<PathBuf, SnapcatError>> + Send>,
+    source: StreamSource,
+    dedup: ContentDedup,
+    tree: StreamTree,
+}
+
+/// The tree of the entries a stream has yielded, see [`SnapcatStream::with_tree`].
+#[cfg(feature = "streaming")]
+struct StreamTree {
+    builder: TreeBuilder,
+    kind: fn(&Path) -> EntryKind,
+    enabled: bool,
+}
+
+#[cfg(feature = "streaming")]
+impl StreamTree {
+    fn new(options: &SnapcatOptions) -> Self {
+        let roots: Vec<&Path> = options.roots().collect();
+        Self {
+            builder: TreeBuilder::new(&roots, options),
+            kind: tree_entry_kind(options),
+            enabled: false,
+        }
+    }
+
+    /// Adds the walked path of a yielded entry, if the tree is enabled.
+    fn insert(&mut self, path: &Path) {
+        if self.enabled {
+            self.builder.insert(path, (self.kind)(path), None);
+        }
+    }
+}
+
+/// An entry read by a stream, with the path it was walked at.
+#[cfg(feature = "streaming")]
+type StreamItem = Result<(PathBuf, FileEntry), SnapcatError>;
+
+/// Paths yielded by the walker (or the explicit path list) for a stream.
+#[cfg(feature = "streaming")]
+type PathIter = Box<dyn Iterator<Item = Result<PathBuf, SnapcatError>> + Send>;
+
+/// State of a [`SnapcatStream`] reading files on the consuming thread.
+#[cfg(feature = "streaming")]
+struct SequentialSource {
+    path_iter: PathIter,
+    cache: PathCache,
     options: SnapcatOptions,
+    filter: ContentFilter,
+    budget: ContentBudget,
+}
+
+/// Where a [`SnapcatStream`] gets its entries from.
+#[cfg(feature = "streaming")]
+enum StreamSource {
+    /// Files are read one at a time on the consuming thread.
+    Sequential(Box<SequentialSource>),
+    /// Files are read ahead in parallel by a producer thread.
+    #[cfg(feature = "parallel")]
+    Prefetched(mpsc::IntoIter<StreamItem>),
+    /// Entries of an archive or Git revision, read up front since its members are loaded in
+    /// memory anyway.
+    #[cfg(any(feature = "archive", feature = "git"))]
+    Buffered(std::vec::IntoIter<(PathBuf, FileEntry)>),
 }
 
 #[cfg(feature = "streaming")]
@@ -239,13 +2570,216 @@ impl SnapcatStream {
     ///
     /// Returns an error if the directory walker cannot be created (e.g., invalid patterns).
     pub fn new(options: SnapcatOptions) -> Result<Self, SnapcatError> {
-        let walker = Walker::new(&options)?;
-        let path_iter = Box::new(walker.into_iter().filter_map(|res| match res {
-            Ok(p) if p.is_file() => Some(Ok(p)),
-            Ok(_) => None,
-            Err(e) => Some(Err(e)),
-        }));
-        Ok(Self { path_iter, options })
+        #[cfg(any(feature = "archive", feature = "git"))]
+        if !matches!(options.source(), Source::Directory(_)) {
+            return Self::buffered(options);
+        }
+        let filter = ContentFilter::new(&options)?;
+        let (path_iter, cache) = stream_paths(&options)?;
+        let budget = ContentBudget::new(&options);
+        let dedup = ContentDedup::new(&options);
+        let tree = StreamTree::new(&options);
+        Ok(Self {
+            source: StreamSource::Sequential(Box::new(SequentialSource {
+                path_iter,
+                cache,
+                options,
+                filter,
+                budget,
+            })),
+            dedup,
+            tree,
+        })
+    }
+
+    /// Creates a streaming iterator that reads up to `buffer` files ahead in parallel.
+    ///
+    /// A background thread walks the tree and reads files in batches of `buffer` on the
+    /// Rayon pool (or a dedicated one with `thread_count` threads), yielding entries as they
+    /// complete, so the order may differ from
+    /// [`SnapcatStream::new`]. At most about `2 * buffer` entries are held in memory; a
+    /// `buffer` of 0 is treated as 1. Dropping the stream stops the background work.
+    /// Only available when both the `streaming` and `parallel` features are enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory walker cannot be created (e.g., invalid patterns).
+    #[cfg(feature = "parallel")]
+    pub fn new_parallel(options: SnapcatOptions, buffer: usize) -> Result<Self, SnapcatError> {
+        #[cfg(any(feature = "archive", feature = "git"))]
+        if !matches!(options.source(), Source::Directory(_)) {
+            return Self::buffered(options);
+        }
+        let buffer = buffer.max(1);
+        let filter = ContentFilter::new(&options)?;
+        let (path_iter, cache) = stream_paths(&options)?;
+        let pool = build_thread_pool(&options)?;
+        let dedup = ContentDedup::new(&options);
+        let tree = StreamTree::new(&options);
+        let (sender, receiver) = mpsc::sync_channel(buffer);
+        std::thread::spawn(move || {
+            let prefetch =
+                || prefetch_parallel(path_iter, &cache, &options, &filter, buffer, &sender);
+            match pool {
+                Some(pool) => pool.install(prefetch),
+                None => prefetch(),
+            }
+        });
+        Ok(Self {
+            source: StreamSource::Prefetched(receiver.into_iter()),
+            dedup,
+            tree,
+        })
+    }
+
+    /// Makes the stream build the tree of the files it yields as it goes, available from
+    /// [`tree_so_far`](Self::tree_so_far) and [`into_tree`](Self::into_tree).
+    ///
+    /// The tree is rendered like [`SnapcatResult::tree`], but only from the yielded
+    /// entries: empty directories and files left out of the results (e.g. with
+    /// [`BinaryHandling::Skip`]) do not appear, and entries that failed to read are not
+    /// listed. Once the stream is drained, it otherwise matches the tree of [`snapcat`].
+    pub fn with_tree(mut self) -> Self {
+        self.tree.enabled = true;
+        self
+    }
+
+    /// Renders the tree of the entries yielded so far (only the root label unless
+    /// [`with_tree`](Self::with_tree) is set).
+    pub fn tree_so_far(&self) -> String {
+        let mut out = String::new();
+        self.tree.builder.render(&mut out);
+        out
+    }
+
+    /// Consumes the stream, returning the tree of the entries it yielded; see
+    /// [`with_tree`](Self::with_tree). Entries not yet yielded are not read.
+    pub fn into_tree(self) -> String {
+        self.tree_so_far()
+    }
+}
+
+#[cfg(all(feature = "streaming", any(feature = "archive", feature = "git")))]
+impl SnapcatStream {
+    /// Creates a stream over the entries of an archive or Git source, which are all read
+    /// first.
+    fn buffered(options: SnapcatOptions) -> Result<Self, SnapcatError> {
+        let filter = ContentFilter::new(&options)?;
+        walk_started(&options);
+        let members = read_members(&options, &filter)?.unwrap_or_default();
+        // Entry paths may be relative or stripped; the tree needs the paths as walked.
+        let walked: HashMap<PathBuf, PathBuf> = members
+            .iter()
+            .filter(|member| !member.is_dir)
+            .map(|member| {
+                let path = options.root.join(&member.path);
+                (entry_path(path.clone(), &options), path)
+            })
+            .collect();
+        let mut result = SnapcatResult::default();
+        snapcat_members(members, &options, &filter, &mut result)?;
+        let entries: Vec<_> = result
+            .files
+            .into_iter()
+            .map(|entry| {
+                let path = walked.get(&entry.path).unwrap_or(&entry.path).clone();
+                (path, entry)
+            })
+            .collect();
+        // The entries are already deduplicated by `snapcat_members`.
+        Ok(Self {
+            source: StreamSource::Buffered(entries.into_iter()),
+            dedup: ContentDedup::disabled(),
+            tree: StreamTree::new(&options),
+        })
+    }
+}
+
+/// Returns the file paths a stream should process, and what the walk learned about them.
+///
+/// With `max_files` or a `sort_order`, the walk completes up front so the paths can be
+/// sorted and capped.
+#[cfg(feature = "streaming")]
+fn stream_paths(options: &SnapcatOptions) -> Result<(PathIter, PathCache), SnapcatError> {
+    let cache = PathCache::default();
+    let paths: PathIter = match explicit_paths(options, &cache) {
+        Some(paths) => Box::new(until_cancelled(paths.map(Ok), options.cancel.clone())),
+        None => {
+            let mut walker = Walker::new(options)?;
+            walker.cache = cache.clone();
+            Box::new(walker.into_iter().filter_map(|res| match res {
+                Ok(p) if p.is_file() => Some(Ok(p)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            }))
+        }
+    };
+    if options.max_files.is_none() && options.sort_order == SortOrder::WalkOrder {
+        return Ok((paths, cache));
+    }
+    let mut file_paths = paths.collect::<Result<Vec<_>, _>>()?;
+    sort_files(&mut file_paths, options.sort_order, |path| cache.size(path));
+    apply_max_files(&mut file_paths, options);
+    let paths = until_cancelled(file_paths.into_iter().map(Ok), options.cancel.clone());
+    Ok((Box::new(paths), cache))
+}
+
+/// Producer loop of [`SnapcatStream::new_parallel`]: reads batches of `buffer` files in
+/// parallel and sends each entry as soon as it is ready.
+///
+/// The content budget is still applied in walk order. Returns once all paths are processed
+/// or the receiving stream has been dropped.
+#[cfg(all(feature = "streaming", feature = "parallel"))]
+fn prefetch_parallel(
+    mut path_iter: PathIter,
+    cache: &PathCache,
+    options: &SnapcatOptions,
+    filter: &ContentFilter,
+    buffer: usize,
+    sender: &mpsc::SyncSender<StreamItem>,
+) {
+    let mut budget = ContentBudget::new(options);
+    loop {
+        let mut paths = Vec::with_capacity(buffer);
+        for item in path_iter.by_ref().take(buffer) {
+            match item {
+                Ok(path) => paths.push(path),
+                Err(e) => {
+                    if sender.send(Err(e)).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+        if paths.is_empty() {
+            return;
+        }
+
+        let stats: Vec<_> = paths
+            .par_iter()
+            .map(|path| cache.stat(path, options))
+            .collect();
+        let admitted = budget.admit_all(&stats, options);
+        let sent = paths
+            .into_par_iter()
+            .zip(stats)
+            .zip(admitted)
+            .try_for_each_with(sender.clone(), |sender, ((path, stat), admitted)| {
+                let outcome = stat.and_then(|metadata| {
+                    process_stated_file(path.clone(), metadata, admitted, cache, options, filter)
+                });
+                match outcome
+                    .map(|outcome| filter.apply(outcome).entry.map(|entry| (path, entry)))
+                    .transpose()
+                {
+                    // The stream was dropped; the error only stops the remaining work.
+                    Some(item) => sender.send(item).map_err(drop),
+                    None => Ok(()),
+                }
+            });
+        if sent.is_err() {
+            return;
+        }
     }
 }
 
@@ -257,34 +2791,248 @@ impl Iterator for SnapcatStream {
     ///
     /// Each item is a `Result` that may contain an error if reading that particular file fails.
     fn next(&mut self) -> Option<Self::Item> {
-        let path = match self.path_iter.next()? {
-            Ok(p) => p,
-            Err(e) => return Some(Err(e)),
-        };
+        let item = self.next_entry()?;
+        Some(item.map(|(path, mut entry)| {
+            self.dedup.apply(&mut entry);
+            self.tree.insert(&path);
+            entry
+        }))
+    }
+}
 
-        let result = (|| {
-            let (content, is_binary) = read_file_content(
-                &path,
-                self.options.binary_detection,
-                self.options.file_size_limit,
-            )?;
-            let size = if self.options.include_file_size {
-                Some(
-                    fs::metadata(&path)
-                        .map_err(|e| SnapcatError::io(&path, e))?
-                        .len(),
-                )
-            } else {
-                None
-            };
-            Ok(FileEntry {
-                path,
-                content,
-                is_binary,
-                size,
-            })
-        })();
+#[cfg(feature = "streaming")]
+impl SnapcatStream {
+    /// Reads the next entry from the source, before deduplication.
+    fn next_entry(&mut self) -> Option<StreamItem> {
+        match &mut self.source {
+            StreamSource::Sequential(source) => loop {
+                let path = match source.path_iter.next()? {
+                    Ok(p) => p,
+                    Err(e) => return Some(Err(e)),
+                };
+
+                let outcome = process_file(
+                    path.clone(),
+                    &source.cache,
+                    &source.options,
+                    &source.filter,
+                    &mut source.budget,
+                );
+                match outcome.map(|outcome| source.filter.apply(outcome)) {
+                    Ok(FileOutcome {
+                        entry: Some(entry), ..
+                    }) => return Some(Ok((path, entry))),
+                    Ok(_) => continue,
+                    Err(e) => return Some(Err(e)),
+                }
+            },
+            #[cfg(feature = "parallel")]
+            StreamSource::Prefetched(entries) => entries.next(),
+            #[cfg(any(feature = "archive", feature = "git"))]
+            StreamSource::Buffered(entries) => entries.next().map(Ok),
+        }
+    }
+}
+
+/// Maximum number of files read concurrently by [`snapcat_async`].
+#[cfg(feature = "async")]
+const ASYNC_READ_CONCURRENCY: usize = 32;
+
+/// Asynchronous variant of [`snapcat`] built on Tokio.
+///
+/// The directory walk runs on a blocking task so it does not stall the runtime, and file
+/// contents are read with `tokio::fs`, with a bounded number of reads in flight. The order
+/// of [`SnapcatResult::files`] matches the blocking API.
+/// Only available when the `async` feature is enabled.
+///
+/// # Errors
+///
+/// Same as [`snapcat`]; additionally returns [`SnapcatError::Walk`] if the walker task panics.
+#[cfg(feature = "async")]
+pub async fn snapcat_async(options: SnapcatOptions) -> Result<SnapcatResult, SnapcatError> {
+    use futures::stream::{self, StreamExt, TryStreamExt};
+
+    #[cfg(feature = "logging")]
+    tracing::debug!(
+        "Starting async snapcat with root: {}",
+        options.root.display()
+    );
+
+    #[cfg(any(feature = "archive", feature = "git"))]
+    if !matches!(options.source(), Source::Directory(_)) {
+        // Archive members are read in memory, so the whole scan runs on a blocking task.
+        return tokio::task::spawn_blocking(move || snapcat(options))
+            .await
+            .map_err(|e| SnapcatError::Walk(format!("Archive task failed: {}", e)))?;
+    }
+
+    let started = Instant::now();
+    let filter = ContentFilter::new(&options)?;
+    walk_started(&options);
+    let mut result = SnapcatResult::default();
+    let (options, tree, walked) = tokio::task::spawn_blocking(move || {
+        let mut tree = String::new();
+        let walked = walk_files(&options, &mut tree)?;
+        Ok::<_, SnapcatError>((options, tree, walked))
+    })
+    .await
+    .map_err(|e| SnapcatError::Walk(format!("Walker task failed: {}", e)))??;
+    let walk_finished = Instant::now();
+    let file_paths = walked.file_paths;
+    let cache = walked.cache;
+    let progress = Progress::new(&options, file_paths.len());
+
+    // Stat everything first so the content budget is applied in walk order.
+    let stats: Vec<_> = stream::iter(&file_paths)
+        .map(|path| async {
+            match cache.cached_stat(path, &options) {
+                Some(stat) => Ok(Some(stat)),
+                None => stat_if_needed_async(path, &options).await,
+            }
+        })
+        .buffered(ASYNC_READ_CONCURRENCY)
+        .collect()
+        .await;
+    let admitted = ContentBudget::new(&options).admit_all(&stats, &options);
+
+    let processed = stream::iter(file_paths.into_iter().zip(stats).zip(admitted))
+        .map(|((path, stat), admitted)| {
+            let cache = &cache;
+            let options = &options;
+            let filter = &filter;
+            let progress = &progress;
+            async move {
+                let outcome = match stat {
+                    Ok(metadata) => {
+                        let is_link = cache.is_symlink(&path);
+                        process_file_async(
+                            path.clone(),
+                            metadata,
+                            admitted,
+                            is_link,
+                            options,
+                            filter,
+                        )
+                        .await
+                    }
+                    Err(e) => Err(e),
+                };
+                finish_file(path, outcome, options, filter, progress)
+            }
+        })
+        .buffered(ASYNC_READ_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    progress.emit(ProgressEvent::Finished);
+    result.tree = tree;
+    assemble_result(&mut result, walked.skipped, processed, &options);
+    result.gitignored = walked.gitignored;
+    if options.tree_annotate_first_line {
+        let roots: Vec<&Path> = options.roots().collect();
+        render_annotated_tree(
+            &mut result,
+            &roots,
+            &walked.tree_entries,
+            &options,
+            tree_entry_kind(&options),
+        )?;
+    }
+    record_durations(&mut result, started, Some(walk_finished));
+    Ok(result)
+}
+
+/// Async counterpart of [`stat_if_needed`] using `tokio::fs`.
+#[cfg(feature = "async")]
+async fn stat_if_needed_async(
+    path: &Path,
+    options: &SnapcatOptions,
+) -> Result<Option<FileStat>, SnapcatError> {
+    if needs_metadata(options) {
+        tokio::fs::metadata(path)
+            .await
+            .map(|m| Some(m.into()))
+            .map_err(|e| SnapcatError::io(path, e))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Reads a single stated file with `tokio::fs` and assembles its [`FileEntry`].
+///
+/// `is_link` tells whether the path was walked as a symbolic link.
+#[cfg(feature = "async")]
+async fn process_file_async(
+    path: PathBuf,
+    metadata: Option<FileStat>,
+    admitted: bool,
+    is_link: bool,
+    options: &SnapcatOptions,
+    filter: &ContentFilter,
+) -> Result<FileOutcome, SnapcatError> {
+    if is_filtered_out(metadata, options) {
+        return Ok(FileOutcome::default());
+    }
+    let link = if is_link {
+        tokio::fs::read_link(&path).await.ok()
+    } else {
+        None
+    };
+    let skip_target = link.is_some() && options.follow_links != FollowLinks::All;
+    if !options.read_content || !admitted || skip_target {
+        let read = if !options.read_content && options.sniff_binary {
+            use tokio::io::AsyncReadExt;
 
-        Some(result)
+            let mut sample = Vec::with_capacity(options.binary_sample_size);
+            tokio::fs::File::open(&path)
+                .await
+                .map_err(|e| SnapcatError::io(&path, e))?
+                .take(options.binary_sample_size as u64)
+                .read_to_end(&mut sample)
+                .await
+                .map_err(|e| SnapcatError::io(&path, e))?;
+            FileContent::sniffed(&path, &sample, options)
+        } else if !options.read_content {
+            FileContent::not_read(&path)
+        } else if skip_target {
+            FileContent::omitted(SYMLINK_PLACEHOLDER, false)
+        } else {
+            FileContent::omitted(BUDGET_PLACEHOLDER, false)
+        };
+        return Ok(into_outcome(path, read, metadata, options, filter).with_symlink_target(link));
     }
+    let len = metadata.map(|m| m.len);
+    let size_limit = filter.size_limit(&path, options);
+
+    let too_large = matches!((size_limit, len), (Some(limit), Some(len)) if len > limit);
+    let read = if too_large {
+        // Hash in chunks, like `read_file_content`, without buffering the whole file.
+        let mut hasher = ContentHasher::new(options);
+        if hasher.is_active() {
+            use tokio::io::AsyncReadExt;
+
+            let mut file = tokio::fs::File::open(&path)
+                .await
+                .map_err(|e| SnapcatError::io(&path, e))?;
+            let mut chunk = vec![0; READ_CHUNK_SIZE];
+            loop {
+                let read = file
+                    .read(&mut chunk)
+                    .await
+                    .map_err(|e| SnapcatError::io(&path, e))?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&chunk[..read]);
+            }
+        }
+        FileContent::too_large(options).with_hash(hasher.finish())
+    } else {
+        let bytes = tokio::fs::read(&path)
+            .await
+            .map_err(|e| SnapcatError::io(&path, e))?;
+        content_from_bytes(&path, bytes, size_limit, options)?
+    };
+
+    Ok(into_outcome(path, read, metadata, options, filter).with_symlink_target(link))
 }
@@ -15,6 +15,11 @@ pub enum SnapcatError {
         source: std::io::Error,
     },
 
+    /// An I/O error not tied to a specific path, e.g. converted with `?` from a bare
+    /// [`std::io::Error`].
+    #[error("I/O error: {0}")]
+    IoWithoutPath(#[from] std::io::Error),
+
     /// An error occurred while walking the directory tree.
     ///
     /// This typically wraps errors from the `ignore` crate.
@@ -25,6 +30,21 @@ pub enum SnapcatError {
     #[error("Invalid path: {0}")]
     InvalidPath(String),
 
+    /// A configuration file or string could not be parsed (e.g., malformed TOML or an unknown key).
+    #[error("Config error: {0}")]
+    Config(String),
+
+    /// A text file contains bytes that are not valid UTF-8 (only with
+    /// [`Utf8Policy::Strict`]).
+    ///
+    /// [`Utf8Policy::Strict`]: crate::Utf8Policy::Strict
+    #[error("Invalid UTF-8 in {0}")]
+    InvalidUtf8(PathBuf),
+
+    /// The scan was stopped through the `cancel` flag before it completed.
+    #[error("Scan cancelled")]
+    Cancelled,
+
     /// Binary detection failed for some reason (should not happen under normal circumstances).
     #[error("Binary detection failed")]
     BinaryDetection,
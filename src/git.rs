@@ -0,0 +1,73 @@
+//! Internal module for reading the files of a Git revision as a virtual directory tree.
+
+use crate::engine::ArchiveMember;
+use crate::error::SnapcatError;
+use git2::{ObjectType, Repository, TreeWalkMode, TreeWalkResult};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// File mode Git records for symbolic links.
+const SYMLINK_MODE: i32 = 0o120000;
+
+/// Reads every file and directory committed at `rev` (e.g. `HEAD~1`, a tag or a commit
+/// hash) in the repository at `repo`, without touching its working tree.
+///
+/// Every member carries the commit time as its modification time. Symlinks and submodules
+/// are dropped, as are entries whose name is not valid UTF-8.
+///
+/// # Errors
+///
+/// Returns [`SnapcatError::InvalidPath`] if `repo` is not a Git repository or an object
+/// cannot be read, and [`SnapcatError::Config`] if `rev` does not name a commit.
+pub(crate) fn read_revision(repo: &Path, rev: &str) -> Result<Vec<ArchiveMember>, SnapcatError> {
+    let invalid = |e: git2::Error| SnapcatError::InvalidPath(format!("{}: {}", repo.display(), e));
+    let repository = Repository::open(repo).map_err(invalid)?;
+    let commit = repository
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| SnapcatError::Config(format!("Invalid revision '{}': {}", rev, e)))?;
+    let modified = u64::try_from(commit.time().seconds())
+        .ok()
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+    let tree = commit.tree().map_err(invalid)?;
+
+    let mut members = Vec::new();
+    let mut failure = None;
+    let walked = tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        let Some(name) = entry.name() else {
+            return TreeWalkResult::Skip;
+        };
+        let path = Path::new(dir).join(name);
+        match entry.kind() {
+            Some(ObjectType::Tree) => members.push(ArchiveMember {
+                path,
+                is_dir: true,
+                modified,
+                size: 0,
+                bytes: Vec::new(),
+            }),
+            Some(ObjectType::Blob) if entry.filemode() != SYMLINK_MODE => {
+                match entry.to_object(&repository).and_then(|o| o.peel_to_blob()) {
+                    Ok(blob) => members.push(ArchiveMember {
+                        path,
+                        is_dir: false,
+                        modified,
+                        size: blob.size() as u64,
+                        bytes: blob.content().to_vec(),
+                    }),
+                    Err(e) => {
+                        failure = Some(invalid(e));
+                        return TreeWalkResult::Abort;
+                    }
+                }
+            }
+            _ => {}
+        }
+        TreeWalkResult::Ok
+    });
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    walked.map_err(invalid)?;
+    Ok(members)
+}
@@ -4,13 +4,22 @@
 //! and reading the contents of files with options for binary detection, size limits, and more.
 //!
 //! It provides both a simple blocking API ([`snapcat`]) and a streaming API ([`SnapcatStream`]) when the
-//! `streaming` feature is enabled. Parallel file processing is available with the `parallel` feature.
+//! `streaming` feature is enabled. Parallel file processing is available with the `parallel` feature,
+//! and an async API ([`snapcat_async`]) with the `async` feature.
 //!
 //! # Features
 //!
 //! - `parallel`: Enables parallel processing of files using Rayon.
 //! - `streaming`: Enables a streaming iterator API for processing files one by one.
 //! - `logging`: Enables debug logging via the `tracing` crate.
+//! - `async`: Enables an async scanning API built on Tokio.
+//! - `hash`: Enables SHA-256 content hashes on file entries.
+//! - `encoding`: Enables charset detection and transcoding of non-UTF-8 text files.
+//! - `compression`: Enables gzip compression of output files ending in `.gz`.
+//! - `mime`: Enables MIME type guessing on file entries.
+//! - `schema`: Enables JSON Schema export of the result types ([`result_schema`]).
+//! - `archive`: Enables reading `.zip` and `.tar` archives as a [`Source`].
+//! - `git`: Enables reading a Git revision as a [`Source`].
 //!
 //! # Example
 //!
@@ -32,8 +41,12 @@
 //! }
 //! ```
 
+#[cfg(feature = "archive")]
+mod archive;
 pub mod engine;
 pub mod error;
+#[cfg(feature = "git")]
+mod git;
 pub mod options;
 pub mod output;
 pub mod tree;
@@ -41,8 +54,23 @@ pub mod types;
 
 #[cfg(feature = "streaming")]
 pub use engine::SnapcatStream;
-pub use engine::snapcat;
+#[cfg(feature = "async")]
+pub use engine::snapcat_async;
+pub use engine::{IgnoreMatcher, snapcat, snapcat_into, walk};
 pub use error::SnapcatError;
-pub use options::{BinaryDetection, SnapcatBuilder, SnapcatOptions};
-pub use output::{OutputFormat, format_result, write_result_to_file};
-pub use types::{FileEntry, SnapcatResult};
+#[cfg(feature = "git")]
+pub use options::GitRevision;
+pub use options::{
+    BinaryDetection, BinaryDetector, BinaryHandling, Callback, ErrorMode, FileCallback,
+    FollowLinks, LineEnding, ProgressCallback, SnapcatBuilder, SnapcatOptions, SortOrder, Source,
+    TreeRootLabel, TreeStyle, Utf8Policy, WalkErrorPolicy,
+};
+pub use output::{
+    DirLayout, FormatOptions, OutputFormat, format_result, write_result, write_result_to_dir,
+    write_result_to_dir_with, write_result_to_file, write_result_with,
+};
+#[cfg(feature = "schema")]
+pub use types::result_schema;
+pub use types::{
+    ByteBreakdown, FileEntry, ProgressEvent, ScanStats, SkipReason, SnapcatDiff, SnapcatResult,
+};
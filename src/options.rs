@@ -1,57 +1,609 @@
 //! Configuration options for directory walking and file processing.
 
+use crate::engine::IgnoreMatcher;
+use crate::error::SnapcatError;
+use crate::types::{FileEntry, ProgressEvent};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::fmt;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::SystemTime;
+
+/// Default number of leading bytes inspected for binary detection (4 KiB).
+pub const DEFAULT_BINARY_SAMPLE_SIZE: usize = 4096;
 
 /// Method used to detect whether a file is binary.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BinaryDetection {
-    /// Simple detection: check for null bytes in the first 4 KiB of the file.
+    /// Simple detection: check for null bytes in the sampled bytes of the file (4 KiB by default).
     Simple,
     /// More accurate detection using the `content_inspector` crate.
     Accurate,
     /// No binary detection; all files are treated as text.
     None,
+    /// Decide from the file extension alone, using a built-in set of binary formats
+    /// (`png`, `jpg`, `pdf`, `zip`, `exe`, `so`, `o`, `wasm`, ...).
+    ///
+    /// Files with unknown or no extension are treated as text.
+    Extension,
+    /// Decide with a user-supplied function, given the path and the sampled leading bytes
+    /// (possibly empty); see [`SnapcatBuilder::binary_detector`]. Never serialized.
+    #[serde(skip)]
+    #[cfg_attr(feature = "schema", schemars(skip))]
+    Custom(BinaryDetector),
+}
+
+/// What to do with the content of files detected as binary.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryHandling {
+    /// Replace the content with a `[Binary file, content omitted]` placeholder.
+    Omit,
+    /// Fill the content with the standard base64 of the raw bytes.
+    ///
+    /// Files over `file_size_limit` are still omitted rather than expanded.
+    Base64,
+    /// Leave the file out of [`SnapcatResult::files`]; it still appears in the tree.
+    ///
+    /// [`SnapcatResult::files`]: crate::SnapcatResult::files
+    Skip,
+}
+
+/// How text files containing invalid UTF-8 are handled; see
+/// [`SnapcatBuilder::utf8_policy`].
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Utf8Policy {
+    /// Replace invalid byte sequences with U+FFFD.
+    #[default]
+    Lossy,
+    /// Fail the file with [`SnapcatError::InvalidUtf8`], subject to `on_error`.
+    Strict,
+    /// Treat the file as binary, applying `binary_handling` to it.
+    TreatInvalidAsBinary,
+}
+
+/// How per-file read failures are handled during a scan.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorMode {
+    /// Abort the scan on the first file that cannot be read.
+    Fail,
+    /// Keep going, recording a placeholder entry and the error in [`SnapcatResult::errors`].
+    ///
+    /// [`SnapcatResult::errors`]: crate::SnapcatResult::errors
+    Collect,
+}
+
+/// How errors reported by the directory walker are handled.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WalkErrorPolicy {
+    /// Abort the scan with [`SnapcatError::Walk`] on the first walker error.
+    Fail,
+    /// Drop the failing entry (e.g. a directory that cannot be read) and keep walking.
+    Skip,
+}
+
+/// Which symbolic links the walk follows; see [`SnapcatBuilder::follow_links`].
+///
+/// In TOML, `true` and `false` are accepted for `All` and `None`.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(from = "FollowLinksRepr")]
+pub enum FollowLinks {
+    /// Do not follow symlinks (the default); symlinked files are listed without reading
+    /// their targets.
+    #[default]
+    None,
+    /// Follow symlinks to both directories and files, reading the targets of symlinked
+    /// files.
+    All,
+    /// Traverse symlinked directories, but list symlinked files without reading their
+    /// targets.
+    DirsOnly,
+}
+
+impl From<bool> for FollowLinks {
+    fn from(yes: bool) -> Self {
+        if yes { Self::All } else { Self::None }
+    }
+}
+
+/// Serialized form of [`FollowLinks`], which also accepts a plain boolean.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FollowLinksRepr {
+    Bool(bool),
+    Mode(FollowLinksMode),
+}
+
+/// The named variants of [`FollowLinks`].
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Deserialize)]
+enum FollowLinksMode {
+    None,
+    All,
+    DirsOnly,
+}
+
+impl From<FollowLinksRepr> for FollowLinks {
+    fn from(repr: FollowLinksRepr) -> Self {
+        match repr {
+            FollowLinksRepr::Bool(yes) => yes.into(),
+            FollowLinksRepr::Mode(FollowLinksMode::None) => Self::None,
+            FollowLinksRepr::Mode(FollowLinksMode::All) => Self::All,
+            FollowLinksRepr::Mode(FollowLinksMode::DirsOnly) => Self::DirsOnly,
+        }
+    }
+}
+
+/// How the first line of the tree labels the root directory.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TreeRootLabel {
+    /// `.` followed by the root as a comment, e.g. `.  # ./myproject`.
+    #[default]
+    Dot,
+    /// The root directory's own name, e.g. `myproject/`.
+    Name,
+    /// The root's absolute path, e.g. `/home/me/myproject/`.
+    FullPath,
+}
+
+/// Characters used to draw the branches of the tree.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TreeStyle {
+    /// Box-drawing characters: `├── `, `└── ` and `│   `.
+    #[default]
+    Unicode,
+    /// Plain ASCII for terminals without box-drawing glyphs: `|-- `, `` `-- `` and `|   `.
+    Ascii,
+    /// Indentation only, four spaces per level.
+    Spaces,
+}
+
+/// Order in which files are processed and listed in [`SnapcatResult::files`].
+///
+/// [`SnapcatResult::files`]: crate::SnapcatResult::files
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SortOrder {
+    /// The walker's traversal order, which may differ between platforms.
+    #[default]
+    WalkOrder,
+    /// Lexicographic by path.
+    PathAsc,
+    /// Largest files first, ties broken by path.
+    SizeDesc,
+    /// Grouped by file extension, then by path; files without an extension come first.
+    ExtensionThenName,
+}
+
+/// Line ending that text content is normalized to; see
+/// [`SnapcatBuilder::normalize_line_endings`].
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineEnding {
+    /// Unix line endings (`\n`).
+    Lf,
+    /// Windows line endings (`\r\n`).
+    Crlf,
+}
+
+/// Where the files of a snapshot come from; see [`SnapcatBuilder::source`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// A directory on disk, walked from `root` (the default).
+    Directory(PathBuf),
+    /// A `.zip`, `.tar`, `.tar.gz` or `.tgz` archive, read without extracting it.
+    /// Only available when the `archive` feature is enabled.
+    #[cfg(feature = "archive")]
+    Archive(PathBuf),
+    /// The files committed at revision `rev` (e.g. `HEAD~1`, a tag or a commit hash) of the
+    /// Git repository at `repo`, read without a checkout.
+    /// Only available when the `git` feature is enabled.
+    #[cfg(feature = "git")]
+    Git {
+        /// Path of the repository (its working tree or `.git` directory).
+        repo: PathBuf,
+        /// Revision to snapshot, in any syntax `git rev-parse` accepts.
+        rev: String,
+    },
+}
+
+/// A revision of a Git repository to snapshot; see [`Source::Git`].
+#[cfg(feature = "git")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitRevision {
+    /// Path of the repository.
+    pub repo: PathBuf,
+    /// Revision to snapshot.
+    pub rev: String,
+}
+
+/// A shareable callback stored in [`SnapcatOptions`].
+///
+/// Wraps an `Arc<dyn Fn>` so the options stay `Clone` and `Debug`; callbacks are never
+/// serialized.
+pub struct Callback<F: ?Sized>(pub Arc<F>);
+
+impl<F: ?Sized> Clone for Callback<F> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
 }
 
+/// Callbacks are equal if they share the same function.
+impl<F: ?Sized> PartialEq for Callback<F> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<F: ?Sized> Eq for Callback<F> {}
+
+impl<F: ?Sized> fmt::Debug for Callback<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Callback(..)")
+    }
+}
+
+/// Callback invoked for each processed file; see [`SnapcatBuilder::on_file`].
+pub type FileCallback = Callback<dyn Fn(&FileEntry) + Send + Sync>;
+
+/// Function deciding whether a file is binary; see [`SnapcatBuilder::binary_detector`].
+pub type BinaryDetector = Callback<dyn Fn(&Path, &[u8]) -> bool + Send + Sync>;
+
+/// Callback receiving [`ProgressEvent`]s; see [`SnapcatBuilder::progress`].
+pub type ProgressCallback = Callback<dyn Fn(ProgressEvent) + Send + Sync>;
+
 /// Configuration options for a snapcat operation.
 ///
-/// This struct can be constructed directly or via the [`SnapcatBuilder`].
+/// This struct can be constructed directly, via the [`SnapcatBuilder`], or loaded from a
+/// TOML config file with [`SnapcatOptions::from_toml_file`]. Missing fields fall back to
+/// their defaults; unknown fields are rejected.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SnapcatOptions {
     /// The root directory to start walking from.
     pub root: PathBuf,
+    /// Additional roots walked after `root`, each shown as a top-level node of the tree.
+    pub extra_roots: Vec<PathBuf>,
     /// Whether to respect `.gitignore` files.
     pub respect_gitignore: bool,
-    /// Maximum depth to walk (None means unlimited).
+    /// Whether to respect the global excludes file (`core.excludesFile`), with
+    /// `respect_gitignore`.
+    pub git_global: bool,
+    /// Whether to respect the repository's `.git/info/exclude`, with `respect_gitignore`.
+    pub git_exclude: bool,
+    /// Whether to list the paths excluded by `.gitignore` files in
+    /// [`SnapcatResult::gitignored`](crate::SnapcatResult::gitignored).
+    pub report_gitignore_exclusions: bool,
+    /// Maximum depth to walk (None means unlimited). The root's direct children are at
+    /// depth 1, unless `depth_includes_root` is set.
     pub max_depth: Option<usize>,
+    /// Whether the root itself counts as the first level of `max_depth`.
+    pub depth_includes_root: bool,
     /// Whether to include hidden files and directories (those starting with a dot).
     pub include_hidden: bool,
-    /// Whether to follow symbolic links.
-    pub follow_links: bool,
+    /// Which symbolic links to follow.
+    pub follow_links: FollowLinks,
     /// List of glob patterns to ignore.
     pub ignore_patterns: Vec<String>,
+    /// Precompiled ignore patterns used instead of `ignore_patterns` (not serialized).
+    #[serde(skip)]
+    pub ignore_matcher: Option<IgnoreMatcher>,
+    /// List of glob patterns a file must match to be included (empty means all files).
+    pub include_patterns: Vec<String>,
+    /// Whether `ignore_patterns` and `include_patterns` match regardless of case.
+    pub case_insensitive_globs: bool,
+    /// Whether `ignore_patterns` and `include_patterns` are matched against full paths
+    /// instead of paths relative to the root.
+    pub glob_match_absolute: bool,
+    /// File extensions to keep (empty means all files); matched case-insensitively,
+    /// with or without a leading dot.
+    pub include_extensions: Vec<String>,
+    /// File extensions to skip; matched case-insensitively, with or without a leading dot.
+    pub exclude_extensions: Vec<String>,
+    /// Extra ignore file names (e.g. `.dockerignore`) read with `.gitignore` syntax.
+    pub custom_ignore_files: Vec<String>,
+    /// Whether to honor `.snapcatignore` files.
+    pub respect_snapcatignore: bool,
+    /// Explicit list of files to process instead of walking `root`.
+    pub explicit_paths: Option<Vec<PathBuf>>,
+    /// Paths longer than this many bytes are skipped during the walk (`None` = no limit).
+    pub max_path_length: Option<usize>,
+    /// Regular expression that the content of included files must match.
+    pub content_matches: Option<String>,
+    /// Regular expressions whose matches are replaced with `[REDACTED]` in file contents.
+    pub redact_patterns: Vec<String>,
+    /// Whether common secrets (AWS keys, tokens, private keys) are redacted as well.
+    pub redact_defaults: bool,
+    /// Glob patterns mapped to the 1-based, inclusive line range kept from matching files.
+    pub line_ranges: Vec<(String, (usize, usize))>,
+    /// Maximum number of files to process, taking the first paths in `sort_order`
+    /// (lexicographic with [`SortOrder::WalkOrder`]).
+    pub max_files: Option<usize>,
+    /// Order in which files are processed and listed.
+    pub sort_order: SortOrder,
     /// Maximum file size (in bytes) to read; files larger than this will have content omitted.
     pub file_size_limit: Option<u64>,
+    /// Glob patterns mapped to the size limit (in bytes) used instead of `file_size_limit`
+    /// for matching files.
+    pub size_limit_overrides: Vec<(String, u64)>,
+    /// Maximum size (in bytes) of each text file's content; longer content is truncated.
+    pub max_content_bytes_per_file: Option<usize>,
+    /// Maximum total size (in bytes) of file contents to read across the whole scan.
+    ///
+    /// Once the next file would exceed it, that file and all later ones are still listed but
+    /// their content is replaced with `[Output size limit reached]`.
+    pub max_total_content_bytes: Option<u64>,
+    /// Text files larger than this (in bytes) are read and decoded in chunks, bounding the
+    /// memory used beyond the content itself (`None` reads files in one go).
+    pub stream_threshold: Option<u64>,
+    /// Minimum file size (in bytes); smaller files are left out of the results.
+    pub min_file_size: Option<u64>,
+    /// Only files modified strictly after this time are included (RFC 3339 in config files).
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "crate::types::rfc3339"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub modified_after: Option<SystemTime>,
+    /// Only files modified strictly before this time are included (RFC 3339 in config files).
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "crate::types::rfc3339"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub modified_before: Option<SystemTime>,
     /// Method used to detect binary files.
     pub binary_detection: BinaryDetection,
+    /// Number of leading bytes inspected for binary detection.
+    ///
+    /// A size of 0 disables sniffing, so every file is treated as text.
+    pub binary_sample_size: usize,
+    /// What to do with the content of binary files.
+    pub binary_handling: BinaryHandling,
+    /// Content of binary files with [`BinaryHandling::Omit`] (`None` uses
+    /// `[Binary file, content omitted]`).
+    pub binary_placeholder: Option<String>,
+    /// Content of files over their size limit (`None` uses
+    /// `[File too large, content omitted]`).
+    pub too_large_placeholder: Option<String>,
+    /// How text files containing invalid UTF-8 are handled.
+    pub utf8_policy: Utf8Policy,
+    /// Line ending text content is converted to (`None` keeps it as is).
+    pub normalize_line_endings: Option<LineEnding>,
+    /// Number of spaces each tab in text content is replaced with (`None` keeps tabs).
+    pub expand_tabs: Option<usize>,
+    /// Whether [`FileEntry::path`] is stored relative to `root`.
+    pub relative_paths: bool,
+    /// Number of leading components removed from entry paths and tree nodes.
+    pub strip_components: usize,
+    /// How the first line of the tree labels the root.
+    pub tree_root_label: TreeRootLabel,
+    /// Characters used to draw the branches of the tree.
+    pub tree_style: TreeStyle,
+    /// Whether each file in the tree is followed by its first non-empty line.
+    pub tree_annotate_first_line: bool,
+    /// Whether directories are listed before files at each level of the tree.
+    pub tree_dirs_first: bool,
+    /// Whether the tree colors directories, symlinks and executables with ANSI codes.
+    pub color_tree: bool,
+    /// Whether directories without any file below them are shown in the tree.
+    pub include_empty_dirs: bool,
+    /// Whether to open files and read their content; when off, entries only carry metadata.
+    pub read_content: bool,
+    /// Whether files are still sniffed for binary content when `read_content` is off.
+    pub sniff_binary: bool,
     /// Whether to include file size in the output.
     pub include_file_size: bool,
+    /// Whether to include the number of lines of text files in the output.
+    pub include_line_count: bool,
+    /// Whether to include the last modification time of each file in the output.
+    pub include_modified: bool,
+    /// Whether to include the Unix permission bits of each file (ignored elsewhere).
+    pub include_permissions: bool,
+    /// Whether to include an estimated LLM token count for each text file.
+    pub include_tokens: bool,
+    /// Whether to include the code block language derived from each file's extension.
+    pub include_language: bool,
+    /// How per-file read failures are handled.
+    pub on_error: ErrorMode,
+    /// How errors reported by the directory walker are handled.
+    pub walk_errors: WalkErrorPolicy,
+    /// Whether to record why files were skipped in [`SnapcatResult::skipped`].
+    ///
+    /// [`SnapcatResult::skipped`]: crate::SnapcatResult::skipped
+    pub collect_skip_reasons: bool,
+    /// Callback invoked after each [`FileEntry`] is built (not serialized).
+    #[serde(skip)]
+    pub on_file: Option<FileCallback>,
+    /// Callback receiving coarse progress events (not serialized).
+    #[serde(skip)]
+    pub progress: Option<ProgressCallback>,
+    /// Flag that aborts the scan with [`SnapcatError::Cancelled`] once set (not
+    /// serialized).
+    #[serde(skip)]
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Whether to include a SHA-256 hash of each file's raw bytes in the output.
+    #[cfg(feature = "hash")]
+    pub include_hash: bool,
+    /// Whether to replace the content of files identical to an earlier file with a
+    /// reference to it.
+    #[cfg(feature = "hash")]
+    pub dedup_content: bool,
+    /// Number of threads for parallel processing; `None` uses the global Rayon pool.
+    #[cfg(feature = "parallel")]
+    pub thread_count: Option<usize>,
+    /// Whether to walk directories on several threads; entries are sorted afterwards.
+    #[cfg(feature = "parallel")]
+    pub parallel_walk: bool,
+    /// Whether to detect the charset of text files and transcode them to UTF-8.
+    #[cfg(feature = "encoding")]
+    pub detect_encoding: bool,
+    /// Whether to include the MIME type guessed from each file's extension.
+    #[cfg(feature = "mime")]
+    pub include_mime: bool,
+    /// Archive whose members are snapshotted instead of walking `root`.
+    #[cfg(feature = "archive")]
+    pub archive: Option<PathBuf>,
+    /// Git revision whose files are snapshotted instead of walking `root`.
+    #[cfg(feature = "git")]
+    pub git: Option<GitRevision>,
 }
 
 impl Default for SnapcatOptions {
     fn default() -> Self {
         Self {
             root: PathBuf::from("."),
+            extra_roots: Vec::new(),
             respect_gitignore: true,
+            git_global: true,
+            git_exclude: true,
+            report_gitignore_exclusions: false,
             max_depth: None,
+            depth_includes_root: false,
             include_hidden: false,
-            follow_links: false,
+            follow_links: FollowLinks::None,
             ignore_patterns: Vec::new(),
+            ignore_matcher: None,
+            include_patterns: Vec::new(),
+            case_insensitive_globs: false,
+            glob_match_absolute: false,
+            include_extensions: Vec::new(),
+            exclude_extensions: Vec::new(),
+            custom_ignore_files: Vec::new(),
+            respect_snapcatignore: true,
+            explicit_paths: None,
+            max_path_length: None,
+            content_matches: None,
+            redact_patterns: Vec::new(),
+            redact_defaults: false,
+            line_ranges: Vec::new(),
+            max_files: None,
+            sort_order: SortOrder::WalkOrder,
             file_size_limit: None,
+            size_limit_overrides: Vec::new(),
+            max_content_bytes_per_file: None,
+            max_total_content_bytes: None,
+            stream_threshold: None,
+            min_file_size: None,
+            modified_after: None,
+            modified_before: None,
             binary_detection: BinaryDetection::Simple,
+            binary_sample_size: DEFAULT_BINARY_SAMPLE_SIZE,
+            binary_handling: BinaryHandling::Omit,
+            binary_placeholder: None,
+            too_large_placeholder: None,
+            utf8_policy: Utf8Policy::Lossy,
+            normalize_line_endings: None,
+            expand_tabs: None,
+            relative_paths: false,
+            strip_components: 0,
+            tree_root_label: TreeRootLabel::Dot,
+            tree_style: TreeStyle::Unicode,
+            tree_annotate_first_line: false,
+            tree_dirs_first: false,
+            color_tree: false,
+            include_empty_dirs: true,
+            read_content: true,
+            sniff_binary: false,
             include_file_size: false,
+            include_line_count: false,
+            include_modified: false,
+            include_permissions: false,
+            include_tokens: false,
+            include_language: false,
+            on_error: ErrorMode::Fail,
+            walk_errors: WalkErrorPolicy::Fail,
+            collect_skip_reasons: false,
+            on_file: None,
+            progress: None,
+            cancel: None,
+            #[cfg(feature = "hash")]
+            include_hash: false,
+            #[cfg(feature = "hash")]
+            dedup_content: false,
+            #[cfg(feature = "parallel")]
+            thread_count: None,
+            #[cfg(feature = "parallel")]
+            parallel_walk: false,
+            #[cfg(feature = "encoding")]
+            detect_encoding: false,
+            #[cfg(feature = "mime")]
+            include_mime: false,
+            #[cfg(feature = "archive")]
+            archive: None,
+            #[cfg(feature = "git")]
+            git: None,
+        }
+    }
+}
+
+impl SnapcatOptions {
+    /// Returns all roots to walk: `root` followed by `extra_roots`.
+    pub fn roots(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(self.root.as_path()).chain(self.extra_roots.iter().map(PathBuf::as_path))
+    }
+
+    /// Returns where the files come from: `archive` or `git` if set, `root` otherwise.
+    pub fn source(&self) -> Source {
+        #[cfg(feature = "archive")]
+        if let Some(archive) = &self.archive {
+            return Source::Archive(archive.clone());
+        }
+        #[cfg(feature = "git")]
+        if let Some(git) = &self.git {
+            return Source::Git {
+                repo: git.repo.clone(),
+                rev: git.rev.clone(),
+            };
         }
+        Source::Directory(self.root.clone())
+    }
+
+    /// Parses options from a TOML string.
+    ///
+    /// Fields not present in the string keep their default values.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SnapcatError::Config`] if the TOML is malformed or contains an unknown key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use snapcat::SnapcatOptions;
+    ///
+    /// let options = SnapcatOptions::from_toml_str("max_depth = 2").unwrap();
+    /// assert_eq!(options.max_depth, Some(2));
+    /// ```
+    pub fn from_toml_str(s: &str) -> Result<Self, SnapcatError> {
+        toml::from_str(s).map_err(|e| SnapcatError::Config(e.to_string()))
+    }
+
+    /// Loads options from a TOML file such as `.snapcat.toml`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SnapcatError::Io`] if the file cannot be read, or [`SnapcatError::Config`]
+    /// if its content is not a valid configuration.
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<Self, SnapcatError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| SnapcatError::io(path, e))?;
+        toml::from_str(&content)
+            .map_err(|e| SnapcatError::Config(format!("{}: {}", path.display(), e)))
     }
 }
 
@@ -80,15 +632,129 @@ impl SnapcatBuilder {
         }
     }
 
+    /// Sets the root directory to start walking from.
+    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.options.root = root.into();
+        self
+    }
+
+    /// Sets where the files come from.
+    ///
+    /// [`Source::Directory`] is the same as [`root`](Self::root). With
+    /// [`Source::Archive`], the archive's members are read in memory and treated as a
+    /// virtual directory rooted at the archive path: the ignore, include and extension
+    /// filters, size limits and binary detection apply to them as to files on disk, while
+    /// `.gitignore` files and extra roots are not used. [`Source::Git`] works the same way
+    /// with the files committed at a revision, rooted at the repository path.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use snapcat::{SnapcatBuilder, Source};
+    ///
+    /// let builder = SnapcatBuilder::new(".").source(Source::Directory("src".into()));
+    /// ```
+    pub fn source(mut self, source: Source) -> Self {
+        #[cfg(feature = "archive")]
+        {
+            self.options.archive = None;
+        }
+        #[cfg(feature = "git")]
+        {
+            self.options.git = None;
+        }
+        match source {
+            Source::Directory(root) => self.options.root = root,
+            #[cfg(feature = "archive")]
+            Source::Archive(archive) => {
+                self.options.root = archive.clone();
+                self.options.archive = Some(archive);
+            }
+            #[cfg(feature = "git")]
+            Source::Git { repo, rev } => {
+                self.options.root = repo.clone();
+                self.options.git = Some(GitRevision { repo, rev });
+            }
+        }
+        self
+    }
+
+    /// Adds another root to walk, e.g. `tests` next to a `src` root.
+    ///
+    /// Every root becomes a top-level node of the combined tree. Files reachable from
+    /// several overlapping roots are only listed once.
+    pub fn add_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.options.extra_roots.push(root.into());
+        self
+    }
+
     /// Sets whether to respect `.gitignore` files.
+    ///
+    /// Turning it off also disables the rules of [`git_global`](Self::git_global) and
+    /// [`git_exclude`](Self::git_exclude).
     pub fn respect_gitignore(mut self, yes: bool) -> Self {
         self.options.respect_gitignore = yes;
         self
     }
 
-    /// Sets the maximum depth to walk.
-    pub fn max_depth(mut self, depth: usize) -> Self {
-        self.options.max_depth = Some(depth);
+    /// Sets whether to respect the global excludes file set by Git's `core.excludesFile`
+    /// (default `true`, only with `respect_gitignore`).
+    pub fn git_global(mut self, yes: bool) -> Self {
+        self.options.git_global = yes;
+        self
+    }
+
+    /// Sets whether to respect the repository's `.git/info/exclude` file (default `true`,
+    /// only with `respect_gitignore`).
+    ///
+    /// Together with [`git_global`](Self::git_global), this hides the same files as
+    /// `git status`.
+    pub fn git_exclude(mut self, yes: bool) -> Self {
+        self.options.git_exclude = yes;
+        self
+    }
+
+    /// Sets whether to list the paths excluded by `.gitignore` files in
+    /// [`SnapcatResult::gitignored`](crate::SnapcatResult::gitignored), to tell them apart
+    /// from paths excluded by snapcat's own patterns.
+    ///
+    /// An ignored directory is listed once, without its contents. Only has an effect with
+    /// `respect_gitignore`; rules from `.git/info/exclude` and the global excludes file
+    /// (see `git_exclude` and `git_global`) still apply but are not reported.
+    pub fn report_gitignore_exclusions(mut self, yes: bool) -> Self {
+        self.options.report_gitignore_exclusions = yes;
+        self
+    }
+
+    /// Sets the maximum depth to walk; `None` removes the limit.
+    ///
+    /// The root is at depth 0 and its direct children at depth 1, so `max_depth(0)` yields
+    /// no entries at all, `max_depth(1)` only the root's direct children, and
+    /// `max_depth(2)` also their children. Directories at the last level are listed in the
+    /// tree without their contents. See
+    /// [`depth_includes_root`](Self::depth_includes_root) to count the root as a level.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use snapcat::SnapcatBuilder;
+    ///
+    /// let shallow = SnapcatBuilder::new(".").max_depth(1).build();
+    /// assert_eq!(shallow.max_depth, Some(1));
+    /// let unlimited = SnapcatBuilder::new(".").max_depth(None).build();
+    /// assert_eq!(unlimited.max_depth, None);
+    /// ```
+    pub fn max_depth(mut self, depth: impl Into<Option<usize>>) -> Self {
+        self.options.max_depth = depth.into();
+        self
+    }
+
+    /// Sets whether the root counts as the first level of `max_depth` (default `false`).
+    ///
+    /// When `true`, `max_depth(1)` yields no entries and `max_depth(2)` the root's direct
+    /// children, as if the root were at depth 1.
+    pub fn depth_includes_root(mut self, yes: bool) -> Self {
+        self.options.depth_includes_root = yes;
         self
     }
 
@@ -104,20 +770,201 @@ impl SnapcatBuilder {
         self
     }
 
-    /// Sets whether to follow symbolic links.
-    pub fn follow_links(mut self, yes: bool) -> Self {
-        self.options.follow_links = yes;
+    /// Sets which symbolic links to follow, e.g. `true` (all), `false` (none) or
+    /// [`FollowLinks::DirsOnly`].
+    ///
+    /// Symlinked directories are only traversed with `All` and `DirsOnly`. Symlinked files
+    /// are always listed, with [`FileEntry::symlink_target`] set, but only `All` reads
+    /// their target; otherwise the content is a `[Symlink, content omitted]` placeholder.
+    ///
+    /// [`FileEntry::symlink_target`]: crate::FileEntry::symlink_target
+    pub fn follow_links(mut self, follow: impl Into<FollowLinks>) -> Self {
+        self.options.follow_links = follow.into();
         self
     }
 
     /// Sets the list of glob patterns to ignore.
     ///
-    /// Patterns are matched against the full path. Example: `"*.tmp"`, `"build/*"`.
+    /// Patterns are matched against the path relative to the root (see
+    /// [`glob_match_absolute`](Self::glob_match_absolute)); patterns without a `/` also
+    /// match the file name alone. Example: `"*.tmp"`, `"build/*"`. A leading `!` re-includes paths
+    /// matched by earlier patterns; as in `.gitignore`, the last matching pattern wins,
+    /// e.g. `["*.log", "!keep.log"]`.
     pub fn ignore_patterns(mut self, patterns: Vec<String>) -> Self {
         self.options.ignore_patterns = patterns;
         self
     }
 
+    /// Sets precompiled ignore patterns, used instead of `ignore_patterns`.
+    ///
+    /// Compiling the globs once and cloning the matcher into each scan's options avoids
+    /// recompiling them when the same patterns are used for many scans. Build it with
+    /// [`IgnoreMatcher::new`] or from a [`globset::GlobSet`]; `case_insensitive_globs` does
+    /// not apply to it.
+    pub fn ignore_matcher(mut self, matcher: impl Into<IgnoreMatcher>) -> Self {
+        self.options.ignore_matcher = Some(matcher.into());
+        self
+    }
+
+    /// Sets the list of glob patterns a file must match to be included.
+    ///
+    /// When non-empty, only files matching at least one pattern (and no ignore pattern)
+    /// are kept. Patterns are matched against the path relative to the root. Directories
+    /// are still traversed. Example: `"*.rs"`, `"src/**"`.
+    pub fn include_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.options.include_patterns = patterns;
+        self
+    }
+
+    /// Sets whether `ignore_patterns` and `include_patterns` match regardless of case,
+    /// so that `*.LOG` also matches `error.log`.
+    pub fn case_insensitive_globs(mut self, yes: bool) -> Self {
+        self.options.case_insensitive_globs = yes;
+        self
+    }
+
+    /// Sets whether `ignore_patterns` and `include_patterns` are matched against the full
+    /// path as walked (e.g. `/home/me/project/src/main.rs`) instead of the path relative to
+    /// the root (`src/main.rs`, the default).
+    ///
+    /// Restores the matching of earlier versions, where root-anchored patterns such as
+    /// `"build/*"` needed a leading `*`.
+    pub fn glob_match_absolute(mut self, yes: bool) -> Self {
+        self.options.glob_match_absolute = yes;
+        self
+    }
+
+    /// Sets the file extensions to keep, e.g. `["rs", "toml"]`.
+    ///
+    /// Extensions match case-insensitively and a leading dot is ignored, so `".RS"` is the
+    /// same as `"rs"`. Multi-part extensions such as `"tar.gz"` are supported. Directories
+    /// are still traversed.
+    pub fn include_extensions(
+        mut self,
+        extensions: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.options.include_extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the file extensions to skip, matched like [`include_extensions`](Self::include_extensions).
+    pub fn exclude_extensions(
+        mut self,
+        extensions: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.options.exclude_extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets extra ignore file names, such as `.dockerignore` or `.npmignore`.
+    ///
+    /// Each file found in a directory is applied with `.gitignore` syntax to that directory
+    /// and its descendants. These take precedence over `.gitignore`, and names later in the
+    /// list take precedence over earlier ones.
+    pub fn custom_ignore_files(mut self, names: Vec<String>) -> Self {
+        self.options.custom_ignore_files = names;
+        self
+    }
+
+    /// Sets whether to honor `.snapcatignore` files (default `true`).
+    ///
+    /// They use `.gitignore` syntax and are applied before any [`Self::custom_ignore_files`].
+    pub fn respect_snapcatignore(mut self, yes: bool) -> Self {
+        self.options.respect_snapcatignore = yes;
+        self
+    }
+
+    /// Sets an explicit list of files to process, bypassing the directory walk.
+    ///
+    /// Relative paths are resolved against the root. Walk settings such as ignore patterns
+    /// do not apply, while binary detection and size limits do. Paths that are not files
+    /// (e.g. deleted ones from `git diff --name-only`) are skipped. The tree shows just
+    /// these paths.
+    pub fn explicit_paths(mut self, paths: Option<Vec<PathBuf>>) -> Self {
+        self.options.explicit_paths = paths;
+        self
+    }
+
+    /// Sets the maximum length (in bytes) of walked paths (default `None`, no limit).
+    ///
+    /// Longer paths, e.g. beyond the 260 byte limit of some Windows APIs, are skipped during
+    /// the walk together with their contents instead of failing later with an I/O error.
+    /// They are recorded as [`SkipReason::PathTooLong`] with `collect_skip_reasons`.
+    ///
+    /// [`SkipReason::PathTooLong`]: crate::SkipReason::PathTooLong
+    pub fn max_path_length(mut self, max: Option<usize>) -> Self {
+        self.options.max_path_length = max;
+        self
+    }
+
+    /// Sets a regular expression (e.g. `TODO`) that file contents must match.
+    ///
+    /// Files whose text does not match are dropped from the results but stay in the tree.
+    /// Binary and omitted files never match. The pattern is matched against the content as
+    /// it appears in the entry, i.e. after truncation by `max_content_bytes_per_file`.
+    pub fn content_matches(mut self, pattern: Option<String>) -> Self {
+        self.options.content_matches = pattern;
+        self
+    }
+
+    /// Sets regular expressions whose matches are replaced with `[REDACTED]` in the
+    /// content of text files, e.g. to share snapshots without leaking API keys.
+    ///
+    /// If a pattern has a capture group named `secret`, only that group is replaced, e.g.
+    /// `token=(?P<secret>\w+)` keeps `token=`. Content is redacted before it is truncated
+    /// and before `content_matches` and `line_ranges` apply; hashes still cover the real
+    /// bytes.
+    pub fn redact_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.options.redact_patterns = patterns;
+        self
+    }
+
+    /// Sets whether built-in patterns for common secrets are redacted too (default
+    /// `false`): AWS access key IDs, GitHub tokens, PEM private keys, and the values of
+    /// assignments such as `api_key = ...`, `token: ...` or `password=...`.
+    pub fn redact_defaults(mut self, yes: bool) -> Self {
+        self.options.redact_defaults = yes;
+        self
+    }
+
+    /// Keeps only `lines` (1-based, inclusive) of the text files matching `pattern`.
+    ///
+    /// The pattern is matched against the entry path and the path relative to its root,
+    /// e.g. `"src/main.rs"` or `"*.rs"`; when several patterns match, the one added first
+    /// wins. The omitted lines are replaced by `[... lines N-M omitted ...]` markers, and
+    /// [`FileEntry::line_range`] records the lines kept. `line_count` still counts the
+    /// whole file.
+    pub fn line_range(mut self, pattern: impl Into<String>, lines: RangeInclusive<usize>) -> Self {
+        self.options
+            .line_ranges
+            .push((pattern.into(), (*lines.start(), *lines.end())));
+        self
+    }
+
+    /// Sets the maximum number of files to process (default `None`, no limit).
+    ///
+    /// File paths are sorted by `sort_order` (lexicographically with
+    /// [`SortOrder::WalkOrder`]) and only the first `max` are processed, so the selection
+    /// does not depend on walk order or on parallelism. The remaining files
+    /// still appear in the tree and are recorded as [`SkipReason::FileLimit`] with
+    /// `collect_skip_reasons`.
+    ///
+    /// [`SkipReason::FileLimit`]: crate::SkipReason::FileLimit
+    pub fn max_files(mut self, max: Option<usize>) -> Self {
+        self.options.max_files = max;
+        self
+    }
+
+    /// Sets the order in which files are processed and listed (default
+    /// [`SortOrder::WalkOrder`]).
+    ///
+    /// Any other order makes the output reproducible across platforms, e.g. for snapshot
+    /// comparisons in CI. The tree is always sorted by name.
+    pub fn sort_order(mut self, order: SortOrder) -> Self {
+        self.options.sort_order = order;
+        self
+    }
+
     /// Sets the maximum file size (in bytes) to read.
     ///
     /// Files larger than this will have their content replaced with an omission message.
@@ -126,20 +973,446 @@ impl SnapcatBuilder {
         self
     }
 
+    /// Reads files matching `pattern` up to `limit` bytes, instead of `file_size_limit`.
+    ///
+    /// The pattern is matched like in [`line_range`](Self::line_range), and the first
+    /// matching pattern wins, e.g. to read large `*.csv` files in full while capping
+    /// `*.min.js`. Files over their limit have their content omitted.
+    pub fn size_limit_override(mut self, pattern: impl Into<String>, limit: u64) -> Self {
+        self.options
+            .size_limit_overrides
+            .push((pattern.into(), limit));
+        self
+    }
+
+    /// Sets the maximum size (in bytes) of each text file's content.
+    ///
+    /// Unlike `file_size_limit`, which omits the content, longer text is cut at the last
+    /// UTF-8 character boundary within the limit, followed by a `… [truncated]` marker, and
+    /// `FileEntry::truncated` is set. Binary files are not affected.
+    pub fn max_content_bytes_per_file(mut self, limit: Option<usize>) -> Self {
+        self.options.max_content_bytes_per_file = limit;
+        self
+    }
+
+    /// Sets the maximum total size (in bytes) of file contents to read across the scan.
+    ///
+    /// Files are charged their on-disk size in walk order. Once the next file would exceed
+    /// the budget, it and every later file keep their path but get an
+    /// `[Output size limit reached]` placeholder. Applies to the streaming API as well.
+    pub fn max_total_content_bytes(mut self, limit: Option<u64>) -> Self {
+        self.options.max_total_content_bytes = limit;
+        self
+    }
+
+    /// Sets the size (in bytes) above which text files are read in chunks (default `None`).
+    ///
+    /// Such files are decoded as UTF-8 piece by piece straight into their content, instead
+    /// of first loading all raw bytes, which bounds peak memory for huge files read without
+    /// a size limit. Characters split across chunks are decoded correctly, and the content
+    /// is the same as when reading in one go. Applies to the blocking and streaming APIs;
+    /// with `detect_encoding`, files are still read whole.
+    pub fn stream_threshold(mut self, threshold: Option<u64>) -> Self {
+        self.options.stream_threshold = threshold;
+        self
+    }
+
+    /// Sets the minimum file size (in bytes) to include, e.g. `Some(1)` to drop empty files.
+    ///
+    /// Smaller files are left out of [`SnapcatResult::files`] but still appear in the tree.
+    /// Applies to the streaming API as well.
+    ///
+    /// [`SnapcatResult::files`]: crate::SnapcatResult::files
+    pub fn min_file_size(mut self, size: Option<u64>) -> Self {
+        self.options.min_file_size = size;
+        self
+    }
+
+    /// Only includes files modified strictly after `time`, e.g. for incremental snapshots.
+    ///
+    /// Other files are left out of [`SnapcatResult::files`] but still appear in the tree.
+    /// The modification time comes from the same `stat` call used for the other metadata.
+    ///
+    /// [`SnapcatResult::files`]: crate::SnapcatResult::files
+    pub fn modified_after(mut self, time: Option<SystemTime>) -> Self {
+        self.options.modified_after = time;
+        self
+    }
+
+    /// Only includes files modified strictly before `time`; see [`Self::modified_after`].
+    pub fn modified_before(mut self, time: Option<SystemTime>) -> Self {
+        self.options.modified_before = time;
+        self
+    }
+
     /// Sets the binary detection method.
     pub fn binary_detection(mut self, method: BinaryDetection) -> Self {
         self.options.binary_detection = method;
         self
     }
 
+    /// Decides whether files are binary with `detector`, given each file's path and its
+    /// leading `binary_sample_size` bytes; sets `binary_detection` to
+    /// [`BinaryDetection::Custom`].
+    ///
+    /// Useful for domain-specific rules, e.g. flagging files by their magic bytes. The
+    /// sample is empty for empty files. With `detect_encoding`, files starting with a byte
+    /// order mark are treated as text without asking the detector.
+    pub fn binary_detector(
+        mut self,
+        detector: impl Fn(&Path, &[u8]) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.options.binary_detection = BinaryDetection::Custom(Callback(Arc::new(detector)));
+        self
+    }
+
+    /// Sets how many leading bytes are inspected for binary detection (default 4096).
+    ///
+    /// Larger windows catch null bytes that appear later in a file; a size of 0 disables
+    /// sniffing, so every file is treated as text.
+    pub fn binary_sample_size(mut self, size: usize) -> Self {
+        self.options.binary_sample_size = size;
+        self
+    }
+
+    /// Sets what to do with the content of binary files (default [`BinaryHandling::Omit`]).
+    pub fn binary_handling(mut self, handling: BinaryHandling) -> Self {
+        self.options.binary_handling = handling;
+        self
+    }
+
+    /// Sets the content of binary files whose content is omitted (default
+    /// `[Binary file, content omitted]`), e.g. a sentinel that downstream parsers can key on.
+    pub fn binary_placeholder(mut self, message: Option<String>) -> Self {
+        self.options.binary_placeholder = message;
+        self
+    }
+
+    /// Sets the content of files over their size limit (default
+    /// `[File too large, content omitted]`).
+    pub fn too_large_placeholder(mut self, message: Option<String>) -> Self {
+        self.options.too_large_placeholder = message;
+        self
+    }
+
+    /// Sets how text files containing invalid UTF-8 are handled (default
+    /// [`Utf8Policy::Lossy`]).
+    ///
+    /// With `detect_encoding`, the policy applies to bytes that are invalid in the detected
+    /// encoding. Files over `stream_threshold` are only decoded in chunks with `Lossy`.
+    pub fn utf8_policy(mut self, policy: Utf8Policy) -> Self {
+        self.options.utf8_policy = policy;
+        self
+    }
+
+    /// Sets the line ending text content is converted to (default `None`, left as is).
+    ///
+    /// Both `\r\n` and `\n` line breaks in decoded text are rewritten, which keeps
+    /// snapshots taken on Windows and Unix comparable. Binary and omitted content is not
+    /// touched, and `hash` still covers the raw bytes.
+    pub fn normalize_line_endings(mut self, ending: Option<LineEnding>) -> Self {
+        self.options.normalize_line_endings = ending;
+        self
+    }
+
+    /// Sets the number of spaces each tab character in text content is replaced with
+    /// (default `None`, tabs are kept).
+    ///
+    /// Every tab becomes exactly `width` spaces, wherever it is on the line, so mixed
+    /// indentation renders the same in any viewer. Binary and omitted content is not
+    /// touched, and `hash` still covers the raw bytes.
+    pub fn expand_tabs(mut self, width: Option<usize>) -> Self {
+        self.options.expand_tabs = width;
+        self
+    }
+
+    /// Sets whether [`FileEntry::path`] is stored relative to the root (e.g. `src/lib.rs`),
+    /// matching the names in the tree and keeping absolute paths out of shared output.
+    ///
+    /// Paths that do not lie under the root (e.g. absolute `explicit_paths`) are kept as is.
+    pub fn relative_paths(mut self, yes: bool) -> Self {
+        self.options.relative_paths = yes;
+        self
+    }
+
+    /// Sets how many leading components are removed from each [`FileEntry::path`] and from
+    /// the root-relative paths of the tree, like `tar --strip-components`.
+    ///
+    /// Applies after `relative_paths`. Files with fewer components keep just their file
+    /// name; directories with no components left are not shown in the tree.
+    pub fn strip_components(mut self, count: usize) -> Self {
+        self.options.strip_components = count;
+        self
+    }
+
+    /// Sets how the first line of the tree labels the root (default
+    /// [`TreeRootLabel::Dot`]).
+    ///
+    /// Only applies to single-root scans; with extra roots the header is always `.` and
+    /// each root is labeled by its path.
+    pub fn tree_root_label(mut self, label: TreeRootLabel) -> Self {
+        self.options.tree_root_label = label;
+        self
+    }
+
+    /// Sets the characters used to draw the tree's branches (default
+    /// [`TreeStyle::Unicode`]); [`TreeStyle::Ascii`] suits consoles without box-drawing
+    /// glyphs.
+    pub fn tree_style(mut self, style: TreeStyle) -> Self {
+        self.options.tree_style = style;
+        self
+    }
+
+    /// Sets whether each file node in the tree is followed by ` — ` and the first non-empty
+    /// line of the file, trimmed (default `false`), for a compact overview.
+    ///
+    /// Binary files and files whose content was omitted are not annotated. The tree is
+    /// then rendered after the files are read; this has no effect on streams.
+    pub fn tree_annotate_first_line(mut self, yes: bool) -> Self {
+        self.options.tree_annotate_first_line = yes;
+        self
+    }
+
+    /// Sets whether the tree lists directories before files at each level (default
+    /// `false`, entries interleaved by name), like `tree --dirsfirst`.
+    pub fn tree_dirs_first(mut self, yes: bool) -> Self {
+        self.options.tree_dirs_first = yes;
+        self
+    }
+
+    /// Sets whether the tree colors entry names with ANSI escape codes (default `false`):
+    /// directories in blue, symlinks in cyan and executables in green.
+    ///
+    /// The codes end up in [`SnapcatResult::tree`] as is, so only enable this for output
+    /// that goes to a terminal.
+    ///
+    /// [`SnapcatResult::tree`]: crate::SnapcatResult::tree
+    pub fn color_tree(mut self, yes: bool) -> Self {
+        self.options.color_tree = yes;
+        self
+    }
+
+    /// Sets whether directories without any file below them appear in the tree (default
+    /// `true`), so that scaffolding such as an empty `assets/` is snapshotted faithfully.
+    ///
+    /// When `false`, such directories are left out, including directories whose files were
+    /// all filtered out (e.g. by `include_patterns`). The roots of a multi-root scan are
+    /// always shown.
+    pub fn include_empty_dirs(mut self, yes: bool) -> Self {
+        self.options.include_empty_dirs = yes;
+        self
+    }
+
+    /// Sets whether files are opened to read their content (default `true`).
+    ///
+    /// When `false`, no file is opened: each entry gets an empty `content` with
+    /// `content_omitted` set, and `is_binary` is decided by extension only. Metadata such as
+    /// `size` and `modified` is still filled in when requested, which makes inventories of
+    /// large trees much faster. Content-based options (`content_matches`, `include_hash`,
+    /// line counts) have nothing to work with in this mode.
+    pub fn read_content(mut self, yes: bool) -> Self {
+        self.options.read_content = yes;
+        self
+    }
+
+    /// Sets whether files are sniffed for binary content when `read_content` is off
+    /// (default `false`, binary by extension only).
+    ///
+    /// Each file is opened, only its first `binary_sample_size` bytes are read and checked
+    /// with `binary_detection`, and it is closed again, so `is_binary` is accurate without
+    /// reading whole files. `content` stays empty. Has no effect when `read_content` is on.
+    pub fn sniff_binary(mut self, yes: bool) -> Self {
+        self.options.sniff_binary = yes;
+        self
+    }
+
     /// Sets whether to include file size in the output.
     pub fn include_file_size(mut self, yes: bool) -> Self {
         self.options.include_file_size = yes;
         self
     }
 
+    /// Sets whether to include the number of lines of text files in the output.
+    ///
+    /// Lines are counted as `\n` occurrences; binary and omitted files get no count.
+    pub fn include_line_count(mut self, yes: bool) -> Self {
+        self.options.include_line_count = yes;
+        self
+    }
+
+    /// Sets whether to include the last modification time of each file in the output.
+    pub fn include_modified(mut self, yes: bool) -> Self {
+        self.options.include_modified = yes;
+        self
+    }
+
+    /// Sets whether to record the permission bits of each file in
+    /// [`FileEntry::mode`](crate::FileEntry) (default `false`), e.g. to restore executable
+    /// bits when reproducing a tree. Only has an effect on Unix.
+    pub fn include_permissions(mut self, yes: bool) -> Self {
+        self.options.include_permissions = yes;
+        self
+    }
+
+    /// Sets whether to include an estimated LLM token count for each text file.
+    ///
+    /// The estimate assumes roughly four characters per token; binary and omitted files
+    /// get no estimate. See [`SnapcatResult::total_tokens`] for the total.
+    ///
+    /// [`SnapcatResult::total_tokens`]: crate::SnapcatResult::total_tokens
+    pub fn include_tokens(mut self, yes: bool) -> Self {
+        self.options.include_tokens = yes;
+        self
+    }
+
+    /// Sets whether to include the language of each file in `FileEntry::language` (e.g.
+    /// `rust` for `.rs`), as used for Markdown code blocks.
+    ///
+    /// The language is derived once from the extension; files with an unknown extension
+    /// get none. The Markdown and HTML formats use it when present.
+    pub fn include_language(mut self, yes: bool) -> Self {
+        self.options.include_language = yes;
+        self
+    }
+
+    /// Sets how per-file read failures are handled.
+    ///
+    /// With [`ErrorMode::Collect`], unreadable files get a placeholder content and the
+    /// scan only fails on walker-level errors.
+    pub fn on_error(mut self, mode: ErrorMode) -> Self {
+        self.options.on_error = mode;
+        self
+    }
+
+    /// Sets how errors reported by the directory walker are handled (default
+    /// [`WalkErrorPolicy::Fail`]).
+    ///
+    /// With [`WalkErrorPolicy::Skip`], entries the walker cannot access, such as a directory
+    /// without read permission or a dangling symlink with `follow_links`, are left out and
+    /// the rest of the tree is still walked.
+    pub fn walk_errors(mut self, policy: WalkErrorPolicy) -> Self {
+        self.options.walk_errors = policy;
+        self
+    }
+
+    /// Sets whether to record why files were skipped in [`SnapcatResult::skipped`].
+    ///
+    /// Covers files dropped by ignore/include patterns or for being hidden, and files whose
+    /// content was omitted for being too large or binary. Files excluded by `.gitignore`
+    /// rules are not recorded. Streaming does not collect skip reasons.
+    ///
+    /// [`SnapcatResult::skipped`]: crate::SnapcatResult::skipped
+    pub fn collect_skip_reasons(mut self, yes: bool) -> Self {
+        self.options.collect_skip_reasons = yes;
+        self
+    }
+
+    /// Sets a callback invoked after each [`FileEntry`] is built, e.g. to report progress.
+    ///
+    /// With the `parallel` feature the callback may be called concurrently from several
+    /// threads and in any order.
+    pub fn on_file(mut self, callback: impl Fn(&FileEntry) + Send + Sync + 'static) -> Self {
+        self.options.on_file = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    /// Sets a callback receiving [`ProgressEvent`]s, e.g. to drive a progress bar.
+    ///
+    /// Events are reported by [`snapcat`](crate::snapcat) and `snapcat_async`; calls are
+    /// serialized so indices always arrive in increasing order.
+    pub fn progress(mut self, callback: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        self.options.progress = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    /// Sets a flag that cancels the scan when set to `true` from another thread.
+    ///
+    /// The flag is checked between walker entries and before each file is finished; the
+    /// scan then fails with [`SnapcatError::Cancelled`] and streams yield that error once
+    /// before ending. With the `parallel` feature, files already being read on other
+    /// threads still complete, so cancellation is best-effort.
+    pub fn cancel(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.options.cancel = Some(flag);
+        self
+    }
+
+    /// Sets whether to include a SHA-256 hash of each file's raw bytes in the output.
+    ///
+    /// Binary and oversized files are hashed too. Only available with the `hash` feature.
+    #[cfg(feature = "hash")]
+    pub fn include_hash(mut self, yes: bool) -> Self {
+        self.options.include_hash = yes;
+        self
+    }
+
+    /// Sets whether to deduplicate identical file contents.
+    ///
+    /// Files are compared by the SHA-256 of their raw bytes. The first occurrence keeps its
+    /// content; each later one gets `[Duplicate of <path>]` as content and the first file's
+    /// path in [`FileEntry::duplicate_of`]. Hashes are computed for this even without
+    /// `include_hash`, but only reported with it. Only available with the `hash` feature.
+    ///
+    /// [`FileEntry::duplicate_of`]: crate::FileEntry::duplicate_of
+    #[cfg(feature = "hash")]
+    pub fn dedup_content(mut self, yes: bool) -> Self {
+        self.options.dedup_content = yes;
+        self
+    }
+
+    /// Sets the number of threads used to process files in parallel.
+    ///
+    /// With `Some(n)`, files are processed on a dedicated Rayon pool of `n` threads instead of
+    /// the global pool, so host applications can cap CPU usage; `Some(1)` processes files one
+    /// at a time. `Some(0)` lets Rayon pick the thread count. Only available with the
+    /// `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn thread_count(mut self, threads: Option<usize>) -> Self {
+        self.options.thread_count = threads;
+        self
+    }
+
+    /// Sets whether to walk directories on several threads (default: false).
+    ///
+    /// Speeds up discovery in very large trees. The same filters apply, and the entries are
+    /// sorted by path once the walk completes, so the result does not depend on thread
+    /// scheduling; unlike a sequential walk, all paths are collected before any file is
+    /// processed, even when streaming. Uses `thread_count` threads when set. Only available
+    /// with the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn parallel_walk(mut self, yes: bool) -> Self {
+        self.options.parallel_walk = yes;
+        self
+    }
+
+    /// Sets whether to detect the charset of text files and transcode them to UTF-8.
+    ///
+    /// Files with a byte order mark (e.g. UTF-16) are decoded accordingly and never flagged
+    /// as binary; other non-UTF-8 files are guessed from the sniff buffer. The detected
+    /// charset is reported in `FileEntry::encoding`. Only available with the `encoding`
+    /// feature.
+    #[cfg(feature = "encoding")]
+    pub fn detect_encoding(mut self, yes: bool) -> Self {
+        self.options.detect_encoding = yes;
+        self
+    }
+
+    /// Sets whether to include the MIME type guessed from each file's extension in
+    /// `FileEntry::mime`. Only available with the `mime` feature.
+    #[cfg(feature = "mime")]
+    pub fn include_mime(mut self, yes: bool) -> Self {
+        self.options.include_mime = yes;
+        self
+    }
+
     /// Builds the final [`SnapcatOptions`].
     pub fn build(self) -> SnapcatOptions {
         self.options
     }
 }
+
+impl From<SnapcatOptions> for SnapcatBuilder {
+    /// Creates a builder starting from existing options, e.g. ones loaded from a config file.
+    fn from(options: SnapcatOptions) -> Self {
+        Self { options }
+    }
+}
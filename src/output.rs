@@ -1,10 +1,16 @@
 //! Output formatting for snapcat results.
 //!
-//! Flexible and clean formatting for [`SnapcatResult`] into Markdown, plain text, or JSON.
+//! Flexible and clean formatting for [`SnapcatResult`] into Markdown, plain text, JSON, YAML, or HTML.
+//! Output can be built as a `String` or streamed incrementally to any [`io::Write`].
 
-use crate::{SnapcatError, SnapcatResult};
-use std::fs;
-use std::path::Path;
+use crate::{FileEntry, SnapcatError, SnapcatResult};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
 
 /// Supported output formats.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -12,6 +18,12 @@ pub enum OutputFormat {
     Markdown,
     Text,
     Json,
+    /// JSON Lines: a `{"tree": ...}` object followed by one [`FileEntry`] object per line.
+    ///
+    /// [`FileEntry`]: crate::FileEntry
+    JsonLines,
+    Yaml,
+    Html,
 }
 
 impl OutputFormat {
@@ -20,90 +32,566 @@ impl OutputFormat {
             OutputFormat::Markdown => "md",
             OutputFormat::Text => "txt",
             OutputFormat::Json => "json",
+            OutputFormat::JsonLines => "jsonl",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Html => "html",
         }
     }
 }
 
+/// Options controlling how a result is rendered by [`write_result_with`].
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+    /// Pretty-print structured formats (indented JSON).
+    pub pretty: bool,
+    /// Start Markdown output with a summary of the root, file count, total size and
+    /// generation time.
+    ///
+    /// Off by default, since the timestamp makes otherwise identical snapshots differ.
+    pub include_header: bool,
+    /// Add a table of contents linking to each file's heading to Markdown output.
+    pub include_toc: bool,
+    /// Extension-to-language mappings for code blocks in Markdown and HTML, e.g.
+    /// `"zig" -> "zig"`.
+    ///
+    /// Consulted before the built-in table; extensions found in neither get no language.
+    pub language_overrides: HashMap<String, String>,
+}
+
 /// Formats the snapcat result into a string.
+///
+/// Convenience wrapper around [`write_result`]; prefer the latter for large results.
 pub fn format_result(result: &SnapcatResult, format: OutputFormat, pretty: bool) -> String {
+    let mut buf = Vec::with_capacity(2048);
+    write_result(result, format, pretty, &mut buf).expect("writing to a Vec cannot fail");
+    String::from_utf8(buf).expect("formatted output is valid UTF-8")
+}
+
+/// Writes the formatted result incrementally to `writer`.
+///
+/// Nothing is buffered beyond what the formatter needs for a single piece of output, so
+/// large results do not have to be held in memory as one string.
+pub fn write_result<W: Write>(
+    result: &SnapcatResult,
+    format: OutputFormat,
+    pretty: bool,
+    writer: &mut W,
+) -> io::Result<()> {
+    let options = FormatOptions {
+        pretty,
+        ..FormatOptions::default()
+    };
+    write_result_with(result, format, &options, writer)
+}
+
+/// Writes the formatted result incrementally to `writer`, as configured by `options`.
+pub fn write_result_with<W: Write>(
+    result: &SnapcatResult,
+    format: OutputFormat,
+    options: &FormatOptions,
+    writer: &mut W,
+) -> io::Result<()> {
     match format {
-        OutputFormat::Markdown => format_markdown(result),
-        OutputFormat::Text => format_text(result),
-        OutputFormat::Json => format_json(result, pretty),
+        OutputFormat::Markdown => write_markdown(result, options, writer),
+        OutputFormat::Text => write_text(result, writer),
+        OutputFormat::Json => write_json(result, options.pretty, writer),
+        OutputFormat::JsonLines => write_json_lines(result, writer),
+        OutputFormat::Yaml => write_yaml(result, writer),
+        OutputFormat::Html => write_html(result, options, writer),
     }
 }
 
 /// Writes the formatted result to a file.
+///
+/// With the `compression` feature, paths ending in `.gz` (e.g. `snapshot.md.gz`) are
+/// gzip-compressed; the decompressed bytes are the same as for an uncompressed file.
 pub fn write_result_to_file(
     result: &SnapcatResult,
     format: OutputFormat,
     path: impl AsRef<Path>,
     pretty: bool,
 ) -> Result<(), SnapcatError> {
-    fs::write(&path, format_result(result, format, pretty))
-        .map_err(|e| SnapcatError::io(path.as_ref(), e))
+    let path = path.as_ref();
+    let file = File::create(path).map_err(|e| SnapcatError::io(path, e))?;
+
+    #[cfg(feature = "compression")]
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut encoder =
+            flate2::write::GzEncoder::new(BufWriter::new(file), flate2::Compression::default());
+        return write_result(result, format, pretty, &mut encoder)
+            .and_then(|()| encoder.finish())
+            .and_then(|mut writer| writer.flush())
+            .map_err(|e| SnapcatError::io(path, e));
+    }
+
+    let mut writer = BufWriter::new(file);
+    write_result(result, format, pretty, &mut writer)
+        .and_then(|()| writer.flush())
+        .map_err(|e| SnapcatError::io(path, e))
+}
+
+/// How [`write_result_to_dir_with`] names the per-file outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DirLayout {
+    /// Mirror each file's path relative to the result root (the default).
+    #[default]
+    Mirrored,
+    /// Put every file directly in the output directory as `NNN-name`, numbered in result
+    /// order.
+    Flat,
+}
+
+/// Writes the result as a directory: one file per [`FileEntry`] holding its content,
+/// mirrored under `dir` by path relative to the result root, plus an `index.<ext>` with the
+/// tree and a link to each written file.
+///
+/// Shorthand for [`write_result_to_dir_with`] with [`DirLayout::Mirrored`].
+///
+/// [`FileEntry`]: crate::FileEntry
+pub fn write_result_to_dir(
+    result: &SnapcatResult,
+    dir: impl AsRef<Path>,
+    format: OutputFormat,
+) -> Result<(), SnapcatError> {
+    write_result_to_dir_with(result, dir, format, DirLayout::Mirrored)
+}
+
+/// Writes the result as a directory of per-file outputs laid out as `layout`, plus an
+/// `index.<ext>` in `format` linking them.
+///
+/// `dir` and any parent directories of the written files are created as needed. Paths
+/// that would escape `dir` keep only their normal components, and paths that collide
+/// (with each other or with the index) get a `-N` suffix before the extension.
+pub fn write_result_to_dir_with(
+    result: &SnapcatResult,
+    dir: impl AsRef<Path>,
+    format: OutputFormat,
+    layout: DirLayout,
+) -> Result<(), SnapcatError> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).map_err(|e| SnapcatError::io(dir, e))?;
+
+    let index_name = PathBuf::from(format!("index.{}", format.extension()));
+    let mut names = DirNames::default();
+    names.claim(&index_name);
+
+    let mut links = Vec::with_capacity(result.files.len());
+    for (i, file) in result.files.iter().enumerate() {
+        let name = names.claim(&entry_name(result, file, i, layout));
+        let target = dir.join(&name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| SnapcatError::io(parent, e))?;
+        }
+        fs::write(&target, &file.content).map_err(|e| SnapcatError::io(&target, e))?;
+        links.push((file.path.as_path(), name));
+    }
+
+    let index = dir.join(index_name);
+    let file = File::create(&index).map_err(|e| SnapcatError::io(&index, e))?;
+    let mut writer = BufWriter::new(file);
+    write_dir_index(result, format, &links, &mut writer)
+        .and_then(|()| writer.flush())
+        .map_err(|e| SnapcatError::io(&index, e))
 }
 
 // ----------------------- Internal helpers -----------------------
 
-/// Wrap content in a code block with optional language
-fn code_block(content: &str, lang: &str) -> String {
-    let mut s = String::new();
-    s.push_str(&format!("```{}\n", lang));
-    s.push_str(content);
+/// Relative output path of a file entry, before collisions are resolved
+fn entry_name(
+    result: &SnapcatResult,
+    file: &crate::FileEntry,
+    index: usize,
+    layout: DirLayout,
+) -> PathBuf {
+    let name = file
+        .path
+        .file_name()
+        .map_or_else(|| "file".into(), |n| n.to_string_lossy().into_owned());
+    match layout {
+        DirLayout::Flat => PathBuf::from(format!("{:03}-{}", index + 1, name)),
+        DirLayout::Mirrored => {
+            let relative = file.path.strip_prefix(&result.root).unwrap_or(&file.path);
+            let clean: PathBuf = relative
+                .components()
+                .filter_map(|c| match c {
+                    Component::Normal(part) => Some(part),
+                    _ => None,
+                })
+                .collect();
+            if clean.as_os_str().is_empty() {
+                PathBuf::from(name)
+            } else {
+                clean
+            }
+        }
+    }
+}
+
+/// Output paths already taken in a [`write_result_to_dir_with`] directory
+#[derive(Default)]
+struct DirNames {
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl DirNames {
+    /// Reserves `name`, or the first free `stem-N.ext` variant of it, and returns it
+    fn claim(&mut self, name: &Path) -> PathBuf {
+        let mut candidate = name.to_path_buf();
+        let mut n = 1;
+        while !self.is_free(&candidate) {
+            let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+            let file_name = match name.extension() {
+                Some(ext) => format!("{}-{}.{}", stem, n, ext.to_string_lossy()),
+                None => format!("{}-{}", stem, n),
+            };
+            candidate = name.with_file_name(file_name);
+            n += 1;
+        }
+        self.dirs
+            .extend(candidate.ancestors().skip(1).map(Path::to_path_buf));
+        self.files.insert(candidate.clone());
+        candidate
+    }
+
+    /// Whether a file can be written at `name` without clashing with a written file or a
+    /// directory holding one
+    fn is_free(&self, name: &Path) -> bool {
+        !self.files.contains(name)
+            && !self.dirs.contains(name)
+            && !name.ancestors().skip(1).any(|a| self.files.contains(a))
+    }
+}
+
+/// Writes the index of a [`write_result_to_dir_with`] directory: the tree, then each
+/// file's original path and where its content was written
+fn write_dir_index<W: Write>(
+    result: &SnapcatResult,
+    format: OutputFormat,
+    links: &[(&Path, PathBuf)],
+    w: &mut W,
+) -> io::Result<()> {
+    #[derive(Serialize)]
+    struct Link<'a> {
+        path: &'a Path,
+        file: String,
+    }
+    #[derive(Serialize)]
+    struct Index<'a> {
+        root: &'a Path,
+        tree: &'a str,
+        files: Vec<Link<'a>>,
+    }
+
+    // Links use forward slashes on every platform.
+    let href = |name: &Path| -> String {
+        name.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+    match format {
+        OutputFormat::Markdown => {
+            write_code_block(w, &result.tree, "")?;
+            for (path, name) in links {
+                let text = path.display().to_string();
+                let text = text.replace('[', "\\[").replace(']', "\\]");
+                writeln!(w, "- [{}](<{}>)", text, href(name))?;
+            }
+            Ok(())
+        }
+        OutputFormat::Text => {
+            w.write_all(result.tree.as_bytes())?;
+            if !result.tree.ends_with('\n') {
+                w.write_all(b"\n")?;
+            }
+            w.write_all(b"\n")?;
+            for (path, name) in links {
+                writeln!(w, "{} -> {}", path.display(), href(name))?;
+            }
+            Ok(())
+        }
+        OutputFormat::Html => {
+            w.write_all(b"<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n")?;
+            w.write_all(b"<title>Snapshot index</title>\n</head>\n<body>\n")?;
+            write!(w, "<pre>{}</pre>\n<ul>\n", html_escape(&result.tree))?;
+            for (path, name) in links {
+                writeln!(
+                    w,
+                    "<li><a href=\"{}\">{}</a></li>",
+                    html_escape(&href(name)),
+                    html_escape(&path.display().to_string())
+                )?;
+            }
+            w.write_all(b"</ul>\n</body>\n</html>\n")
+        }
+        OutputFormat::JsonLines => {
+            #[derive(Serialize)]
+            struct Header<'a> {
+                root: &'a Path,
+                tree: &'a str,
+            }
+
+            // One compact object per line: the tree, then one per link.
+            let header = Header {
+                root: &result.root,
+                tree: &result.tree,
+            };
+            serde_json::to_writer(&mut *w, &header).map_err(io::Error::other)?;
+            w.write_all(b"\n")?;
+            for (path, name) in links {
+                let link = Link {
+                    path,
+                    file: href(name),
+                };
+                serde_json::to_writer(&mut *w, &link).map_err(io::Error::other)?;
+                w.write_all(b"\n")?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json | OutputFormat::Yaml => {
+            let index = Index {
+                root: &result.root,
+                tree: &result.tree,
+                files: links
+                    .iter()
+                    .map(|(path, name)| Link {
+                        path,
+                        file: href(name),
+                    })
+                    .collect(),
+            };
+            match format {
+                OutputFormat::Yaml => serde_yaml::to_writer(w, &index).map_err(io::Error::other),
+                _ => serde_json::to_writer_pretty(&mut *w, &index)
+                    .map_err(io::Error::other)
+                    .and_then(|()| w.write_all(b"\n")),
+            }
+        }
+    }
+}
+
+/// Write content wrapped in a code block with optional language
+///
+/// Like in CommonMark, the fence is one backtick longer than the longest run of backticks
+/// in the content (and at least three), so embedded fences cannot close the block.
+fn write_code_block<W: Write>(w: &mut W, content: &str, lang: &str) -> io::Result<()> {
+    let fence = "`".repeat(longest_backtick_run(content).max(2) + 1);
+    writeln!(w, "{}{}", fence, lang)?;
+    w.write_all(content.as_bytes())?;
     if !content.ends_with('\n') {
-        s.push('\n');
+        w.write_all(b"\n")?;
     }
-    s.push_str("```\n");
-    s
+    writeln!(w, "{}", fence)
+}
+
+/// Returns the length of the longest run of consecutive backticks in `text`
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
 }
 
-/// Formats as Markdown with tree and file sections
-fn format_markdown(result: &SnapcatResult) -> String {
-    let mut out = String::with_capacity(2048);
+/// Writes Markdown with tree and file sections
+fn write_markdown<W: Write>(
+    result: &SnapcatResult,
+    options: &FormatOptions,
+    w: &mut W,
+) -> io::Result<()> {
+    if options.include_header {
+        write_markdown_header(result, w)?;
+    }
+    if options.include_toc {
+        write_markdown_toc(result, w)?;
+    }
 
     // Tree as code block
-    out.push_str(&code_block(&result.tree, ""));
+    write_code_block(w, &result.tree, "")?;
 
     // Files
     for file in &result.files {
-        let path_str = file.path.display().to_string();
-        let ext = file.path.extension().and_then(|e| e.to_str()).unwrap_or("");
-        out.push_str(&format!("## {}\n\n", path_str));
-        out.push_str(&code_block(&file.content, language_from_extension(ext)));
+        write!(w, "## {}\n\n", file.path.display())?;
+        write_code_block(w, &file.content, language_for(file, options))?;
     }
 
-    out
+    Ok(())
 }
 
-/// Formats as plain text with simple separators
-fn format_text(result: &SnapcatResult) -> String {
-    let mut out = String::with_capacity(2048);
-    out.push_str("Directory Tree:\n");
-    out.push_str(&result.tree);
-    if !result.tree.ends_with('\n') {
-        out.push('\n');
+/// Writes the summary section that opens Markdown output
+fn write_markdown_header<W: Write>(result: &SnapcatResult, w: &mut W) -> io::Result<()> {
+    if result.root.as_os_str().is_empty() {
+        w.write_all(b"# Snapshot\n\n")?;
+    } else {
+        write!(w, "# Snapshot of `{}`\n\n", result.root.display())?;
+    }
+    writeln!(w, "- Files: {}", result.file_count())?;
+    if result.files.iter().any(|f| f.size.is_some()) {
+        writeln!(w, "- Total size: {} bytes", result.total_size())?;
     }
-    out.push_str("\n\nFiles:\n");
+    writeln!(
+        w,
+        "- Generated: {}\n",
+        humantime::format_rfc3339_seconds(SystemTime::now())
+    )
+}
 
+/// Writes a table of contents linking to the `## <path>` heading of each file
+fn write_markdown_toc<W: Write>(result: &SnapcatResult, w: &mut W) -> io::Result<()> {
+    writeln!(w, "## {}\n", TOC_HEADING)?;
+    // The TOC heading itself takes its slug first.
+    let mut slugs = HashMap::from([(heading_slug(TOC_HEADING), 1)]);
     for file in &result.files {
-        out.push_str(&format!("\n--- {} ---\n", file.path.display()));
-        out.push_str(&file.content);
-        if !file.content.ends_with('\n') {
-            out.push('\n');
-        }
+        let heading = file.path.display().to_string();
+        let slug = heading_slug(&heading);
+        let count = slugs.entry(slug.clone()).or_insert(0);
+        let anchor = if *count == 0 {
+            slug
+        } else {
+            format!("{}-{}", slug, count)
+        };
+        *count += 1;
+        let text = heading.replace('[', "\\[").replace(']', "\\]");
+        writeln!(w, "- [{}](#{})", text, anchor)?;
     }
+    w.write_all(b"\n")
+}
 
-    out
+/// Title of the Markdown table of contents
+const TOC_HEADING: &str = "Table of Contents";
+
+/// Computes the GitHub-style anchor slug of a heading: lowercase, with punctuation removed
+/// and spaces replaced by hyphens
+fn heading_slug(heading: &str) -> String {
+    heading
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
 }
 
-/// Formats as JSON, optionally pretty-printed
-fn format_json(result: &SnapcatResult, pretty: bool) -> String {
+/// Writes plain text with simple separators
+fn write_text<W: Write>(result: &SnapcatResult, w: &mut W) -> io::Result<()> {
+    write!(w, "{}", result)
+}
+
+/// Renders the plain text format, as produced by [`OutputFormat::Text`].
+impl fmt::Display for SnapcatResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Directory Tree:\n")?;
+        f.write_str(&self.tree)?;
+        if !self.tree.ends_with('\n') {
+            f.write_str("\n")?;
+        }
+        f.write_str("\n\nFiles:\n")?;
+
+        for file in &self.files {
+            write!(f, "\n--- {} ---\n", file.path.display())?;
+            f.write_str(&file.content)?;
+            if !file.content.ends_with('\n') {
+                f.write_str("\n")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes JSON, optionally pretty-printed
+fn write_json<W: Write>(result: &SnapcatResult, pretty: bool, w: &mut W) -> io::Result<()> {
     if pretty {
-        serde_json::to_string_pretty(result).expect("JSON serialization failed")
+        serde_json::to_writer_pretty(w, result).map_err(io::Error::other)
     } else {
-        serde_json::to_string(result).expect("JSON serialization failed")
+        serde_json::to_writer(w, result).map_err(io::Error::other)
+    }
+}
+
+/// Writes JSON Lines: a metadata object with the tree, then one object per file.
+///
+/// Each line is a complete JSON document, so `pretty` does not apply.
+fn write_json_lines<W: Write>(result: &SnapcatResult, w: &mut W) -> io::Result<()> {
+    #[derive(Serialize)]
+    struct Metadata<'a> {
+        tree: &'a str,
     }
+
+    serde_json::to_writer(&mut *w, &Metadata { tree: &result.tree }).map_err(io::Error::other)?;
+    w.write_all(b"\n")?;
+    for file in &result.files {
+        serde_json::to_writer(&mut *w, file).map_err(io::Error::other)?;
+        w.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Writes YAML; the multi-line tree is emitted as a literal block scalar
+fn write_yaml<W: Write>(result: &SnapcatResult, w: &mut W) -> io::Result<()> {
+    serde_yaml::to_writer(w, result).map_err(io::Error::other)
+}
+
+/// Writes a self-contained HTML document with one collapsible block per file
+fn write_html<W: Write>(
+    result: &SnapcatResult,
+    options: &FormatOptions,
+    w: &mut W,
+) -> io::Result<()> {
+    w.write_all(b"<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n")?;
+    w.write_all(b"<title>snapcat</title>\n")?;
+    w.write_all(b"<style>body{font-family:sans-serif}pre{background:#f6f8fa;padding:8px;overflow:auto}summary{cursor:pointer;font-family:monospace}</style>\n")?;
+    w.write_all(b"</head>\n<body>\n")?;
+
+    // Tree
+    writeln!(w, "<pre class=\"tree\">{}</pre>", html_escape(&result.tree))?;
+
+    // Files
+    for file in &result.files {
+        let path_str = file.path.display().to_string();
+        let lang = language_for(file, options);
+        w.write_all(b"<details>\n")?;
+        writeln!(w, "<summary>{}</summary>", html_escape(&path_str))?;
+        if lang.is_empty() {
+            w.write_all(b"<pre><code>")?;
+        } else {
+            write!(w, "<pre><code class=\"language-{}\">", html_escape(lang))?;
+        }
+        w.write_all(html_escape(&file.content).as_bytes())?;
+        w.write_all(b"</code></pre>\n</details>\n")?;
+    }
+
+    w.write_all(b"</body>\n</html>\n")
+}
+
+/// Escapes HTML-special characters in text
+fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Picks the code block language of a file: the user override for its extension, then
+/// `FileEntry::language`, then the built-in table
+fn language_for<'a>(file: &'a FileEntry, options: &'a FormatOptions) -> &'a str {
+    let ext = file.path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match (options.language_overrides.get(ext), &file.language) {
+        (Some(lang), _) | (None, Some(lang)) => lang,
+        (None, None) => language_from_extension(ext),
+    }
+}
+
+/// Returns the built-in code block language for a path's extension, or `""` if unknown
+pub(crate) fn language_for_path(path: &Path) -> &'static str {
+    language_from_extension(path.extension().and_then(|e| e.to_str()).unwrap_or(""))
 }
 
 /// Maps file extensions to Markdown code block languages
@@ -1,37 +1,393 @@
 //! Internal module for building a tree representation from a list of paths.
 
 use crate::error::SnapcatError;
-use std::path::{Path, PathBuf};
+use crate::options::{SnapcatOptions, TreeRootLabel, TreeStyle};
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf};
 
-/// Builds a visual tree string from a root directory and a list of entries.
+/// What a tree entry is, which decides its marker and, with colors on, its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum EntryKind {
+    #[default]
+    File,
+    Dir,
+    Symlink,
+    Executable,
+}
+
+impl EntryKind {
+    /// Tells directories from files by following symlinks, as the tree did before colors.
+    pub(crate) fn plain(path: &Path) -> Self {
+        if path.is_dir() { Self::Dir } else { Self::File }
+    }
+
+    /// Inspects the entry itself, so that symlinks and executables can be told apart.
+    pub(crate) fn detailed(path: &Path) -> Self {
+        let Ok(metadata) = std::fs::symlink_metadata(path) else {
+            return Self::plain(path);
+        };
+        if metadata.file_type().is_symlink() {
+            Self::Symlink
+        } else if metadata.is_dir() {
+            Self::Dir
+        } else if is_executable(&metadata) {
+            Self::Executable
+        } else {
+            Self::File
+        }
+    }
+
+    /// Returns the ANSI color sequence for this kind, if it is colored.
+    fn color(self) -> Option<&'static str> {
+        match self {
+            Self::File => None,
+            Self::Dir => Some(DIR_COLOR),
+            Self::Symlink => Some(SYMLINK_COLOR),
+            Self::Executable => Some(EXECUTABLE_COLOR),
+        }
+    }
+}
+
+/// ANSI color of directory names: bold blue.
+const DIR_COLOR: &str = "\x1b[1;34m";
+/// ANSI color of symlink names: bold cyan.
+const SYMLINK_COLOR: &str = "\x1b[1;36m";
+/// ANSI color of executable file names: bold green.
+const EXECUTABLE_COLOR: &str = "\x1b[1;32m";
+/// ANSI sequence resetting the color.
+const COLOR_RESET: &str = "\x1b[0m";
+
+/// Returns whether any execute permission bit is set.
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+/// Returns whether the file is executable; there is no execute bit outside Unix.
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// A node of the directory hierarchy reconstructed from the flat entry list.
+#[derive(Default)]
+struct Node {
+    kind: EntryKind,
+    /// Text shown after the name, e.g. the first line of a file.
+    note: Option<String>,
+    children: BTreeMap<OsString, Node>,
+}
+
+impl Node {
+    /// Inserts a root-relative path, creating intermediate directory nodes as needed.
+    ///
+    /// The first `strip` components are left out; directories with no components left are
+    /// not inserted at all.
+    fn insert(&mut self, relative: &Path, kind: EntryKind, note: Option<String>, strip: usize) {
+        if kind == EntryKind::Dir && normal_components(relative).count() <= strip {
+            return;
+        }
+        let relative = strip_leading_components(relative, strip);
+        let mut node = self;
+        let mut components = normal_components(&relative).peekable();
+        while let Some(name) = components.next() {
+            node = node.children.entry(name.to_os_string()).or_default();
+            if components.peek().is_none() {
+                node.kind = kind;
+                node.note.clone_from(&note);
+            } else if node.kind == EntryKind::File {
+                node.kind = EntryKind::Dir;
+            }
+        }
+    }
+
+    /// Removes the child directories that contain no files, directly or below; returns
+    /// whether this node is left without children.
+    fn prune_empty_dirs(&mut self) -> bool {
+        self.children
+            .retain(|_, child| child.kind != EntryKind::Dir || !child.prune_empty_dirs());
+        self.children.is_empty()
+    }
+
+    /// Returns whether the node is shown as a directory: anything with children is one,
+    /// whatever the filesystem says.
+    fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Dir || !self.children.is_empty()
+    }
+
+    /// Renders the children of this node, one line per entry, each preceded by a newline.
+    ///
+    /// Children are sorted by name, with directories before files if `dirs_first` is set.
+    /// With `color`, names are wrapped in the ANSI color of their [`EntryKind`].
+    fn render(
+        &self,
+        prefix: &str,
+        connectors: &Connectors,
+        color: bool,
+        dirs_first: bool,
+        out: &mut String,
+    ) {
+        let mut children: Vec<_> = self.children.iter().collect();
+        if dirs_first {
+            // Stable, so names stay sorted within each group.
+            children.sort_by_key(|(_, child)| !child.is_dir());
+        }
+        let count = children.len();
+        for (i, (name, child)) in children.into_iter().enumerate() {
+            let is_last = i + 1 == count;
+            let connector = if is_last {
+                connectors.last
+            } else {
+                connectors.branch
+            };
+            let marker = if child.is_dir() { "/" } else { "" };
+            let kind = if child.kind == EntryKind::File && child.is_dir() {
+                EntryKind::Dir
+            } else {
+                child.kind
+            };
+            let style = kind.color().filter(|_| color);
+            out.push('\n');
+            out.push_str(prefix);
+            out.push_str(connector);
+            out.push_str(style.unwrap_or(""));
+            out.push_str(&name.to_string_lossy());
+            out.push_str(marker);
+            if style.is_some() {
+                out.push_str(COLOR_RESET);
+            }
+            if let Some(note) = &child.note {
+                out.push_str(" — ");
+                out.push_str(note);
+            }
+            let indent = if is_last {
+                connectors.blank
+            } else {
+                connectors.pipe
+            };
+            child.render(
+                &format!("{}{}", prefix, indent),
+                connectors,
+                color,
+                dirs_first,
+                out,
+            );
+        }
+    }
+}
+
+/// The strings drawing the branches of the tree in a [`TreeStyle`].
+struct Connectors {
+    /// Precedes an entry followed by siblings.
+    branch: &'static str,
+    /// Precedes the last entry of a directory.
+    last: &'static str,
+    /// Indents the entries below a non-last entry.
+    pipe: &'static str,
+    /// Indents the entries below a last entry.
+    blank: &'static str,
+}
+
+impl Connectors {
+    fn new(style: TreeStyle) -> Self {
+        match style {
+            TreeStyle::Unicode => Self {
+                branch: "├── ",
+                last: "└── ",
+                pipe: "│   ",
+                blank: "    ",
+            },
+            TreeStyle::Ascii => Self {
+                branch: "|-- ",
+                last: "`-- ",
+                pipe: "|   ",
+                blank: "    ",
+            },
+            TreeStyle::Spaces => Self {
+                branch: "    ",
+                last: "    ",
+                pipe: "    ",
+                blank: "    ",
+            },
+        }
+    }
+}
+
+/// Returns the named components of a path, skipping root, prefix and `.` components.
+fn normal_components(path: &Path) -> impl Iterator<Item = &OsStr> {
+    path.components().filter_map(|c| match c {
+        Component::Normal(name) => Some(name),
+        _ => None,
+    })
+}
+
+/// Removes the first `count` named components of `path`, like `tar --strip-components`.
+///
+/// Root and prefix components are not counted. If fewer components remain than `count`,
+/// the file name alone is kept.
+pub(crate) fn strip_leading_components(path: &Path, count: usize) -> PathBuf {
+    if count == 0 {
+        return path.to_path_buf();
+    }
+    let names: Vec<&OsStr> = normal_components(path).collect();
+    let start = count.min(names.len().saturating_sub(1));
+    names[start..].iter().collect()
+}
+
+/// Renders the first line of a single-root tree.
+///
+/// [`TreeRootLabel::Name`] and [`TreeRootLabel::FullPath`] resolve the root against the
+/// filesystem so that roots like `.` get a meaningful name; if that fails, the path is used
+/// as given.
+fn root_label(root: &Path, label: TreeRootLabel) -> String {
+    let resolved = || std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    match label {
+        TreeRootLabel::Dot => format!(".  # {}", root.display()),
+        TreeRootLabel::Name => {
+            let resolved = resolved();
+            match resolved.file_name() {
+                Some(name) => format!("{}/", name.to_string_lossy()),
+                None => resolved.display().to_string(),
+            }
+        }
+        TreeRootLabel::FullPath => {
+            let resolved = resolved();
+            let path = resolved.display().to_string();
+            if path.ends_with(std::path::MAIN_SEPARATOR) {
+                path
+            } else {
+                format!("{}/", path)
+            }
+        }
+    }
+}
+
+/// Builds a visual tree string from the root directories and a list of entries, replacing
+/// the contents of `out`.
 ///
-/// The entries are expected to be paths under the root. The output is similar to
-/// the `tree` command, using ASCII characters.
+/// The entries are expected to be paths under one of the roots, and `kind` tells what each
+/// of them is; the rendering follows `strip_components`, `tree_root_label`, `color_tree`
+/// and `include_empty_dirs` from `options`. The hierarchy is reconstructed from the paths,
+/// so intermediate directories appear even if only deep files are listed. Directories are
+/// marked with a trailing `/`.
+/// The output is similar to the `tree` command, using the characters of `tree_style`
+/// (box-drawing by default): the last child of each directory gets `└──`, and vertical bars
+/// are only drawn for ancestors that still have siblings below them. With `color_tree`,
+/// the names of directories, symlinks and executables are wrapped in ANSI colors. Entries
+/// for which `note` returns some text are followed by ` — ` and that text.
+///
+/// Entries are sorted by name at each level, or with `tree_dirs_first`, directories first.
+///
+/// The first line labels a single root as chosen by `tree_root_label` (see
+/// [`root_label`]). With several roots, the first line is `.` and each root becomes a
+/// top-level node labeled with its path, kept even if empty; every entry is placed under
+/// the first root that contains it. The first `strip_components` components of each
+/// root-relative path are left out (see [`strip_leading_components`]).
 ///
 /// # Errors
 ///
 /// Returns an error if any path is invalid (should not happen with proper input).
 pub(crate) fn build_tree_from_entries(
-    root: &Path,
+    out: &mut String,
+    roots: &[&Path],
     entries: &[PathBuf],
-) -> Result<String, SnapcatError> {
-    let mut sorted: Vec<_> = entries.iter().filter(|p| *p != root).collect();
-    sorted.sort_by(|a, b| a.components().cmp(b.components()));
-
-    let mut lines = Vec::new();
-    lines.push(format!(".  # {}", root.display()));
-
-    for entry in sorted {
-        let relative = entry.strip_prefix(root).unwrap_or(entry);
-        let depth = relative.components().count();
-        let prefix = if depth == 0 {
-            String::new()
+    options: &SnapcatOptions,
+    kind: impl Fn(&Path) -> EntryKind,
+    note: impl Fn(&Path) -> Option<String>,
+) -> Result<(), SnapcatError> {
+    let mut tree = TreeBuilder::new(roots, options);
+    for entry in entries.iter().filter(|p| !roots.contains(&p.as_path())) {
+        tree.insert(entry, kind(entry), note(entry));
+    }
+    if !options.include_empty_dirs {
+        tree.prune_empty_dirs();
+    }
+    tree.render(out);
+    Ok(())
+}
+
+/// A tree built up one entry at a time, rendered on demand like
+/// [`build_tree_from_entries`].
+pub(crate) struct TreeBuilder {
+    roots: Vec<PathBuf>,
+    /// The first line of the rendered tree.
+    label: String,
+    strip_components: usize,
+    connectors: Connectors,
+    color: bool,
+    dirs_first: bool,
+    tree: Node,
+}
+
+impl TreeBuilder {
+    /// Creates an empty tree for `roots`, taking the rendering settings from `options`.
+    pub(crate) fn new(roots: &[&Path], options: &SnapcatOptions) -> Self {
+        let mut tree = Node::default();
+        let label = if let [root] = roots {
+            root_label(root, options.tree_root_label)
         } else {
-            "│   ".repeat(depth - 1) + "├── "
+            for root in roots {
+                tree.children
+                    .entry(root.as_os_str().to_os_string())
+                    .or_default()
+                    .kind = EntryKind::Dir;
+            }
+            ".".to_string()
         };
-        let name = relative.file_name().unwrap().to_string_lossy();
-        lines.push(format!("{}{}", prefix, name));
+        Self {
+            roots: roots.iter().map(|root| root.to_path_buf()).collect(),
+            label,
+            strip_components: options.strip_components,
+            connectors: Connectors::new(options.tree_style),
+            color: options.color_tree,
+            dirs_first: options.tree_dirs_first,
+            tree,
+        }
+    }
+
+    /// Adds an entry, which should not be one of the roots, placing it under the first
+    /// root that contains it.
+    pub(crate) fn insert(&mut self, entry: &Path, kind: EntryKind, note: Option<String>) {
+        let strip = self.strip_components;
+        match self.roots.as_slice() {
+            [root] => {
+                let relative = entry.strip_prefix(root).unwrap_or(entry);
+                self.tree.insert(relative, kind, note, strip);
+            }
+            roots => match roots.iter().find(|root| entry.starts_with(root)) {
+                Some(root) => {
+                    let relative = entry.strip_prefix(root).unwrap_or(entry);
+                    let node = self
+                        .tree
+                        .children
+                        .entry(root.as_os_str().to_os_string())
+                        .or_default();
+                    node.insert(relative, kind, note, strip);
+                }
+                None => self.tree.insert(entry, kind, note, strip),
+            },
+        }
     }
 
-    Ok(lines.join("\n"))
+    /// Removes the directories that contain no files; with several roots, the roots
+    /// themselves are kept.
+    fn prune_empty_dirs(&mut self) {
+        if self.roots.len() == 1 {
+            self.tree.prune_empty_dirs();
+        } else {
+            self.tree.children.values_mut().for_each(|root| {
+                root.prune_empty_dirs();
+            });
+        }
+    }
+
+    /// Renders the tree, replacing the contents of `out`.
+    pub(crate) fn render(&self, out: &mut String) {
+        out.clear();
+        out.push_str(&self.label);
+        self.tree
+            .render("", &self.connectors, self.color, self.dirs_first, out);
+    }
 }
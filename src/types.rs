@@ -1,8 +1,14 @@
+use crate::SnapcatOptions;
+use crate::error::SnapcatError;
+use crate::tree::{EntryKind, build_tree_from_entries};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 /// A single file entry with its path, content, and metadata.
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct FileEntry {
     /// The full path to the file.
     pub path: PathBuf,
@@ -13,18 +19,422 @@ pub struct FileEntry {
     pub content: String,
     /// Whether the file was detected as binary.
     pub is_binary: bool,
+    /// Whether `content` is a placeholder instead of the file's real content.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub content_omitted: bool,
+    /// Whether `content` is the standard base64 of a binary file's raw bytes.
+    ///
+    /// Set with [`BinaryHandling::Base64`](crate::BinaryHandling::Base64).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_base64: bool,
+    /// Whether `content` is a preview cut at `max_content_bytes_per_file`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
     /// The size of the file in bytes, if requested.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub size: Option<u64>,
+    /// The number of lines in the file, if requested and the content is text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_count: Option<usize>,
+    /// The 1-based, inclusive lines kept in `content`, if a `line_ranges` pattern matched.
+    ///
+    /// Lines outside the range are replaced by `[... lines N-M omitted ...]` markers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_range: Option<(usize, usize)>,
+    /// Lowercase hex SHA-256 of the raw file bytes, if requested.
+    ///
+    /// Computed from the real file bytes even when `content` is a placeholder.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Whether the path is a symbolic link.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_symlink: bool,
+    /// Where the symbolic link points, as stored in the link (possibly relative).
+    ///
+    /// Unless `follow_links` is [`FollowLinks::All`], the target is not read and `content`
+    /// is a `[Symlink, content omitted]` placeholder.
+    ///
+    /// [`FollowLinks::All`]: crate::FollowLinks::All
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symlink_target: Option<PathBuf>,
+    /// Path of the earlier file with identical content, if this one was deduplicated.
+    ///
+    /// Set with `dedup_content`; `content` is then a `[Duplicate of <path>]` placeholder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<PathBuf>,
+    /// Name of the detected charset of a text file (e.g. `UTF-16LE`), if requested.
+    ///
+    /// The content has already been transcoded to UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// MIME type guessed from the file extension (e.g. `text/x-rust`), if requested.
+    ///
+    /// Binary files get their guessed type too; unknown extensions fall back to
+    /// `text/plain` or `application/octet-stream`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime: Option<String>,
+    /// Code block language derived from the file extension (e.g. `python`), if requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// The last modification time of the file, if requested.
+    ///
+    /// Serialized as an RFC 3339 string (e.g. `2024-01-31T12:00:00Z`).
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "rfc3339")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub modified: Option<SystemTime>,
+    /// The Unix permission bits of the file (e.g. `0o100755`), if requested.
+    ///
+    /// Includes the file type bits, as returned by `PermissionsExt::mode`. Only available
+    /// on Unix; not recorded for archive members.
+    #[cfg(unix)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    /// Estimated number of LLM tokens in the content, if requested and the content is text.
+    ///
+    /// Uses a simple heuristic of roughly four characters per token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_estimate: Option<usize>,
 }
 
 /// The complete result of a snapcat operation.
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct SnapcatResult {
+    /// The root directory the snapshot was taken from.
+    #[serde(default)]
+    pub root: PathBuf,
     /// A visual tree representation of the directory structure.
     ///
     /// This is a string similar to the output of the `tree` command.
     pub tree: String,
     /// A list of all files found, with their content and metadata.
     pub files: Vec<FileEntry>,
+    /// Per-file errors recorded when running with [`ErrorMode::Collect`].
+    ///
+    /// [`ErrorMode::Collect`]: crate::ErrorMode::Collect
+    #[serde(skip)]
+    pub errors: Vec<SnapcatError>,
+    /// Files left out of the results or with omitted content, and why.
+    ///
+    /// Only set when `collect_skip_reasons` is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<Vec<(PathBuf, SkipReason)>>,
+    /// Paths excluded by `.gitignore` files, in walk order.
+    ///
+    /// Only filled when `report_gitignore_exclusions` is enabled; an ignored directory is
+    /// listed without its contents.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gitignored: Vec<PathBuf>,
+    /// How long the scan took, from its start to the assembled result.
+    ///
+    /// Set by [`snapcat`], [`snapcat_into`] and `snapcat_async`; not serialized, so that
+    /// the output stays deterministic.
+    ///
+    /// [`snapcat`]: crate::snapcat
+    /// [`snapcat_into`]: crate::snapcat_into
+    #[serde(skip)]
+    pub duration: Option<Duration>,
+    /// The part of `duration` spent walking the tree; `None` for archive and Git sources.
+    #[serde(skip)]
+    pub walk_duration: Option<Duration>,
+    /// The part of `duration` spent reading files after the walk; `None` for archive and Git
+    /// sources.
+    #[serde(skip)]
+    pub read_duration: Option<Duration>,
+}
+
+/// Returns the JSON Schema of [`SnapcatResult`], describing the JSON output format.
+///
+/// Only available with the `schema` feature.
+#[cfg(feature = "schema")]
+pub fn result_schema() -> serde_json::Value {
+    schemars::schema_for!(SnapcatResult).to_value()
+}
+
+/// Why a file was left out of the results or had its content omitted.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The file exceeded `file_size_limit`; its entry is kept without content.
+    TooLarge,
+    /// The file was detected as binary and its content omitted or the file skipped.
+    Binary,
+    /// The path matched an ignore pattern or did not match the include patterns or
+    /// extension filters.
+    IgnoredGlob,
+    /// The path is hidden and `include_hidden` is off.
+    Hidden,
+    /// The path is longer than `max_path_length`.
+    PathTooLong,
+    /// The file sorted past the `max_files` limit.
+    FileLimit,
+}
+
+impl SnapcatResult {
+    /// Builds a result from file entries obtained elsewhere, e.g. from a VCS API, rendering
+    /// the tree from their paths without touching the filesystem.
+    ///
+    /// Entry paths may be absolute under `root` or relative to it; directories are implied
+    /// by the paths. Combine with [`FileEntry::default`] to fill only the fields you have:
+    ///
+    /// ```
+    /// use snapcat::{FileEntry, SnapcatResult};
+    ///
+    /// let files = vec![FileEntry {
+    ///     path: "src/main.rs".into(),
+    ///     content: "fn main() {}".into(),
+    ///     ..FileEntry::default()
+    /// }];
+    /// let result = SnapcatResult::from_files("myproject", files);
+    /// assert!(result.tree.contains("main.rs"));
+    /// ```
+    pub fn from_files(root: impl Into<PathBuf>, files: Vec<FileEntry>) -> Self {
+        let root = root.into();
+        let paths: Vec<PathBuf> = files.iter().map(|f| f.path.clone()).collect();
+        let mut tree = String::new();
+        build_tree_from_entries(
+            &mut tree,
+            &[root.as_path()],
+            &paths,
+            &SnapcatOptions::default(),
+            |_| EntryKind::File,
+            |_| None,
+        )
+        .expect("building a tree from paths cannot fail");
+        Self {
+            root,
+            tree,
+            files,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the sum of the per-file token estimates.
+    ///
+    /// Files without an estimate (binary, omitted, or when `include_tokens` is off) count as 0.
+    pub fn total_tokens(&self) -> usize {
+        self.files.iter().filter_map(|f| f.token_estimate).sum()
+    }
+
+    /// Returns the number of file entries.
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns the sum of the file sizes in bytes.
+    ///
+    /// Sizes are only recorded with `include_file_size(true)`; entries without one count
+    /// as 0, so the total is 0 when the option is off.
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().filter_map(|f| f.size).sum()
+    }
+
+    /// Splits [`total_size`](Self::total_size) into text, binary and omitted bytes.
+    ///
+    /// Each file counts in exactly one bucket, by its real size on disk: binary files under
+    /// `binary_bytes`, other files with placeholder content (e.g. too large) under
+    /// `omitted_bytes`, and the rest under `text_bytes`. Like `total_size`, this needs
+    /// `include_file_size(true)`; entries without a size count as 0.
+    pub fn byte_breakdown(&self) -> ByteBreakdown {
+        let mut breakdown = ByteBreakdown::default();
+        for file in &self.files {
+            let size = file.size.unwrap_or(0);
+            if file.is_binary {
+                breakdown.binary_bytes += size;
+            } else if file.content_omitted {
+                breakdown.omitted_bytes += size;
+            } else {
+                breakdown.text_bytes += size;
+            }
+        }
+        breakdown
+    }
+
+    /// Computes aggregate statistics over the collected files.
+    ///
+    /// Binary and omitted files are counted, but only files whose real content was read
+    /// contribute to [`ScanStats::total_bytes`].
+    pub fn stats(&self) -> ScanStats {
+        let mut stats = ScanStats::default();
+        for file in &self.files {
+            stats.file_count += 1;
+            if file.is_binary {
+                stats.binary_count += 1;
+            }
+            if file.content_omitted {
+                stats.omitted_count += 1;
+            } else {
+                stats.total_bytes += file.size.unwrap_or(file.content.len() as u64);
+            }
+            let extension = file
+                .path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            *stats.by_extension.entry(extension).or_default() += 1;
+        }
+        stats
+    }
+
+    /// Compares this result with a later snapshot, listing the files added, removed and
+    /// changed in `other`.
+    ///
+    /// Files are matched by their path relative to each result's root, so absolute and
+    /// relative entries compare alike. A file has changed if its hash differs when both
+    /// entries have one, or else if its content or size differs. Paths in the diff are
+    /// relative to the roots and sorted.
+    pub fn diff(&self, other: &SnapcatResult) -> SnapcatDiff {
+        let before = self.files_by_relative_path();
+        let after = other.files_by_relative_path();
+        let mut diff = SnapcatDiff::default();
+        for (path, old) in &before {
+            match after.get(path) {
+                None => diff.removed.push(path.to_path_buf()),
+                Some(new) if old.differs_from(new) => diff.changed.push(path.to_path_buf()),
+                Some(_) => {}
+            }
+        }
+        diff.added = after
+            .keys()
+            .filter(|path| !before.contains_key(*path))
+            .map(|path| path.to_path_buf())
+            .collect();
+        diff
+    }
+
+    /// Computes a SHA-256 over the whole snapshot, as lowercase hex, so that two parties can
+    /// confirm they hold identical snapshots.
+    ///
+    /// The hash covers the sorted list of `(path, content hash, size)` tuples, with paths
+    /// relative to the root and `/`-separated, so it does not depend on where the snapshot
+    /// was taken. The content hash is [`FileEntry::hash`], or the SHA-256 of `content` for
+    /// entries without one; a missing size hashes as empty. Compare hashes of snapshots
+    /// taken with the same options only.
+    #[cfg(feature = "hash")]
+    pub fn manifest_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let hex =
+            |digest: &[u8]| -> String { digest.iter().map(|b| format!("{:02x}", b)).collect() };
+        let mut manifest = Sha256::new();
+        for (path, file) in self.files_by_relative_path() {
+            let path: Vec<_> = path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect();
+            let hash = match &file.hash {
+                Some(hash) => hash.clone(),
+                None => hex(&Sha256::digest(file.content.as_bytes())),
+            };
+            let size = file.size.map(|size| size.to_string()).unwrap_or_default();
+            manifest.update(format!("{}\0{}\0{}\n", path.join("/"), hash, size));
+        }
+        hex(&manifest.finalize())
+    }
+
+    /// Indexes the files by their path relative to the root.
+    fn files_by_relative_path(&self) -> BTreeMap<&Path, &FileEntry> {
+        self.files
+            .iter()
+            .map(|file| {
+                let path = file.path.strip_prefix(&self.root).unwrap_or(&file.path);
+                (path, file)
+            })
+            .collect()
+    }
+}
+
+impl FileEntry {
+    /// Returns whether two snapshots of a file differ, see [`SnapcatResult::diff`].
+    fn differs_from(&self, other: &FileEntry) -> bool {
+        if let (Some(a), Some(b)) = (&self.hash, &other.hash) {
+            return a != b;
+        }
+        let sizes_differ = matches!((self.size, other.size), (Some(a), Some(b)) if a != b);
+        sizes_differ || self.content != other.content
+    }
+}
+
+/// The files that differ between two snapshots, see [`SnapcatResult::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapcatDiff {
+    /// Files only present in the later snapshot.
+    pub added: Vec<PathBuf>,
+    /// Files only present in the earlier snapshot.
+    pub removed: Vec<PathBuf>,
+    /// Files present in both snapshots whose content differs.
+    pub changed: Vec<PathBuf>,
+}
+
+impl SnapcatDiff {
+    /// Returns whether the snapshots hold the same files with the same content.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Aggregate statistics over a [`SnapcatResult`], see [`SnapcatResult::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanStats {
+    /// Total number of files.
+    pub file_count: usize,
+    /// Total bytes of content that was actually read.
+    pub total_bytes: u64,
+    /// Number of files detected as binary.
+    pub binary_count: usize,
+    /// Number of files whose content was replaced by a placeholder.
+    pub omitted_count: usize,
+    /// Number of files per lowercase extension; files without one are counted under `""`.
+    pub by_extension: BTreeMap<String, usize>,
+}
+
+/// Byte totals of a [`SnapcatResult`] by kind of file, see [`SnapcatResult::byte_breakdown`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteBreakdown {
+    /// Bytes of text files whose content is included.
+    pub text_bytes: u64,
+    /// Bytes of files detected as binary.
+    pub binary_bytes: u64,
+    /// Bytes of text files whose content was replaced by a placeholder.
+    pub omitted_bytes: u64,
+}
+
+/// Coarse progress of a [`snapcat`](crate::snapcat) run, reported through the `progress`
+/// option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// The directory walk is about to start.
+    WalkStarted,
+    /// A file has been processed.
+    ///
+    /// `index` is the zero-based number of files processed so far and `total` the number of
+    /// files found by the walk. Indices are reported in increasing order, even in parallel mode.
+    FileProcessed { index: usize, total: usize },
+    /// All files have been processed.
+    Finished,
+}
+
+/// Serde helper for `Option<SystemTime>` values stored as RFC 3339 strings.
+pub(crate) mod rfc3339 {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+    use std::time::SystemTime;
+
+    pub(crate) fn serialize<S: Serializer>(
+        time: &Option<SystemTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => serializer.collect_str(&humantime::format_rfc3339(*time)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<SystemTime>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| humantime::parse_rfc3339_weak(&s).map_err(D::Error::custom))
+            .transpose()
+    }
 }
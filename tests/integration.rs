@@ -1,5 +1,6 @@
 use snapcat::{SnapcatBuilder, snapcat};
 use std::fs;
+use std::process::Command;
 use tempfile::tempdir;
 #[test]
 fn integration_full_flow() {
@@ -17,3 +18,205 @@ fn integration_full_flow() {
         assert!(file.size.is_some());
     }
 }
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn integration_full_flow_async() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/lib.rs"), "pub fn test() {}").unwrap();
+    let options = SnapcatBuilder::new(dir.path())
+        .include_file_size(true)
+        .build();
+    let result = snapcat::snapcat_async(options.clone()).await.unwrap();
+    assert!(result.tree.contains("main.rs"));
+    assert_eq!(result.files.len(), 2);
+    for file in &result.files {
+        assert!(file.size.is_some());
+    }
+    let sync = snapcat(options).unwrap();
+    let async_paths: Vec<_> = result.files.iter().map(|f| &f.path).collect();
+    let sync_paths: Vec<_> = sync.files.iter().map(|f| &f.path).collect();
+    assert_eq!(async_paths, sync_paths);
+}
+#[cfg(all(feature = "async", feature = "hash"))]
+#[tokio::test]
+async fn integration_async_hashes_oversized_files() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("big.txt"), "x".repeat(200_000)).unwrap();
+    let options = SnapcatBuilder::new(dir.path())
+        .file_size_limit(Some(1024))
+        .include_hash(true)
+        .build();
+    let result = snapcat::snapcat_async(options.clone()).await.unwrap();
+    let sync = snapcat(options).unwrap();
+    assert!(result.files[0].content.contains("File too large"));
+    assert!(result.files[0].hash.is_some());
+    assert_eq!(result.files[0].hash, sync.files[0].hash);
+}
+#[test]
+fn integration_cli_output_file_matches_stdout() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/lib.rs"), "pub fn test() {}").unwrap();
+    let out_dir = tempdir().unwrap();
+    for format in ["json", "tree", "paths", "markdown"] {
+        let out_path = out_dir.path().join(format!("out.{}", format));
+        let run = |extra: &[&std::ffi::OsStr]| {
+            let output = Command::new(env!("CARGO_BIN_EXE_snapcat"))
+                .arg(dir.path())
+                .args(["--format", format])
+                .args(extra)
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+            output.stdout
+        };
+        let stdout = run(&[]);
+        let redirected = run(&["--output".as_ref(), out_path.as_os_str()]);
+        assert!(redirected.is_empty());
+        assert_eq!(fs::read(&out_path).unwrap(), stdout, "format {}", format);
+    }
+}
+#[test]
+fn integration_cli_modes() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+    let run = |mode: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_snapcat"))
+            .arg(dir.path())
+            .args(["--mode", mode])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "mode {}", mode);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let normal = run("normal");
+    assert!(normal.starts_with('{'));
+    assert!(normal.contains("fn main() {}"));
+    let tree = run("tree-only");
+    assert!(tree.starts_with(".  # "));
+    assert!(tree.contains("main.rs"));
+    assert!(!tree.contains("fn main() {}"));
+    let paths = run("paths-only");
+    assert_eq!(
+        paths.trim(),
+        dir.path().join("main.rs").display().to_string()
+    );
+    #[cfg(feature = "streaming")]
+    {
+        let streamed = run("streaming");
+        let entry: serde_json::Value = serde_json::from_str(streamed.trim()).unwrap();
+        assert_eq!(entry["content"], "fn main() {}");
+    }
+}
+#[test]
+fn integration_cli_color_disabled_when_piped() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_snapcat"))
+        .arg(dir.path())
+        .args(["--format", "tree", "--color"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let tree = String::from_utf8(output.stdout).unwrap();
+    assert!(tree.contains("src/"));
+    assert!(!tree.contains('\u{1b}'));
+}
+#[test]
+fn integration_cli_ignore_file() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+    fs::write(dir.path().join("debug.log"), "log").unwrap();
+    fs::write(dir.path().join("notes.tmp"), "tmp").unwrap();
+    fs::write(dir.path().join("keep.txt"), "keep").unwrap();
+    let patterns = tempdir().unwrap();
+    let ignore_file = patterns.path().join("ignore");
+    fs::write(&ignore_file, "# build noise\n*.log\n\n  *.tmp  \n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_snapcat"))
+        .arg(dir.path())
+        .args(["--format", "paths", "-I", "keep.txt", "--ignore-file"])
+        .arg(&ignore_file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let paths = String::from_utf8(output.stdout).unwrap();
+    assert!(paths.contains("main.rs"));
+    assert!(!paths.contains("debug.log"));
+    assert!(!paths.contains("notes.tmp"));
+    assert!(!paths.contains("keep.txt"));
+}
+#[test]
+fn integration_cli_no_content() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+    let run = |format: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_snapcat"))
+            .arg(dir.path())
+            .args(["--no-content", "--format", format])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "format {}", format);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let json: serde_json::Value = serde_json::from_str(&run("json")).unwrap();
+    let entry = &json["files"][0];
+    assert_eq!(entry["content"], "");
+    assert_eq!(entry["size"], 12);
+    let markdown = run("markdown");
+    assert!(markdown.contains("main.rs"));
+    assert!(!markdown.contains("fn main() {}"));
+}
+#[test]
+fn integration_cli_stats_subcommand() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+    fs::write(dir.path().join("lib.rs"), "pub fn a() {}").unwrap();
+    fs::write(dir.path().join("README.md"), "# readme").unwrap();
+    let run = |args: &[&std::ffi::OsStr]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_snapcat"))
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let stats = run(&["stats".as_ref(), dir.path().as_os_str()]);
+    assert!(stats.starts_with("Files:        3\nTotal bytes:  33\n"));
+    assert!(stats.contains("\nmd                  1\nrs                  2\n"));
+    assert_eq!(stats, run(&[dir.path().as_os_str(), "--stats".as_ref()]));
+    let paths = run(&[
+        "scan".as_ref(),
+        dir.path().as_os_str(),
+        "--format=paths".as_ref(),
+    ]);
+    assert_eq!(paths.lines().count(), 3);
+    assert_eq!(
+        paths,
+        run(&[dir.path().as_os_str(), "--format=paths".as_ref()])
+    );
+}
+#[test]
+fn integration_cli_diff_subcommand() {
+    let before = tempdir().unwrap();
+    let after = tempdir().unwrap();
+    fs::write(before.path().join("same.txt"), "same").unwrap();
+    fs::write(after.path().join("same.txt"), "same").unwrap();
+    fs::write(before.path().join("edited.txt"), "old").unwrap();
+    fs::write(after.path().join("edited.txt"), "new").unwrap();
+    fs::write(before.path().join("gone.txt"), "").unwrap();
+    fs::write(after.path().join("added.txt"), "").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_snapcat"))
+        .arg("diff")
+        .args([before.path(), after.path()])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "A\tadded.txt\nM\tedited.txt\nD\tgone.txt\n"
+    );
+}